@@ -0,0 +1,207 @@
+use rand::{Rng, SeedableRng};
+
+use crate::cards;
+use crate::game::Settings;
+use crate::mcts::{MctsBot, SearchBudget};
+use crate::player::{new_bot, BotDificulty, Player};
+use crate::state::{Action, Event};
+use crate::table::{Table, Transfer};
+use crate::utils::*;
+
+/// Suggested move with a short explanation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hint
+{
+    pub action    : Action,
+    pub rationale : String,
+}
+
+impl Hint
+{
+    pub fn new(action: Action, hand: & [cards::Card], table: & Table) -> Self
+    {
+        Self {action, rationale: rationale(action, hand, table)}
+    }
+}
+
+impl std::fmt::Display for Hint
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        write!(f, "{}: {}", self.action, self.rationale)
+    }
+}
+
+/// Suggests moves to a human player: the strongest bot follows the game along with him/her and says what it would play
+pub struct Advisor
+{
+    bot : Box<dyn Player>,
+    /// Own random numbers, so asking for hints doesn't change the course of a seeded game
+    rng : GameRng,
+}
+
+impl Advisor
+{
+    pub fn new() -> Self
+    {
+        Self {bot: new_bot(BotDificulty::Expert, MctsBot::DEFAULT_BUDGET), rng: GameRng::seed_from_u64(rand::thread_rng().gen())}
+    }
+
+    /// Same seed gives the same hints in the same positions
+    pub fn seed(mut self, seed: u64) -> Self
+    {
+        self.rng = GameRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Expert bot thinking over every hint within the budget replaces the current bot
+    pub fn budget(mut self, budget: SearchBudget) -> Self
+    {
+        self.bot = new_bot(BotDificulty::Expert, budget);
+        self
+    }
+
+    /// Bot which plays in the player's place
+    pub fn bot(mut self, bot: Box<dyn Player>) -> Self
+    {
+        self.bot = bot;
+        self
+    }
+
+    pub fn notice_trump_card(&mut self, trump_card: cards::Card, holder_name: Option<& str>)
+    {
+        self.bot.notice_trump_card(trump_card, holder_name);
+    }
+
+    pub fn notice_game_start(&mut self, player_index: usize, players_count: usize, settings: & Settings)
+    {
+        self.bot.notice_game_start(player_index, players_count, settings);
+    }
+
+    pub fn notice_event(&mut self, event: & Event)
+    {
+        self.bot.notice_event(event);
+    }
+
+    /// Attack, throw-in or pass, the defender may be taking the cards already
+    pub fn advise_attack(&mut self, hand: & [cards::Card], table: & Table, is_first_attack: bool, is_defender_taking: bool, defender_cards_count: usize) -> Hint
+    {
+        *self.bot.cards_mut() = hand.to_vec();
+        let card = match is_defender_taking
+        {
+            true => self.bot.play_card_to_take(table, defender_cards_count, &mut self.rng),
+            false => self.bot.play_attack_card(table, is_first_attack, defender_cards_count, &mut self.rng),
+        };
+        let action = match card
+        {
+            Some(card) if is_first_attack => Action::Attack(card),
+            Some(card) => Action::ThrowIn(card),
+            None => Action::Pass,
+        };
+        Hint::new(action, hand, table)
+    }
+
+    pub fn advise_defense(&mut self, hand: & [cards::Card], table: & Table) -> Hint
+    {
+        *self.bot.cards_mut() = hand.to_vec();
+        let action = match self.bot.play_defense_card(table, &mut self.rng)
+        {
+            Some((attack_card_index, card)) => Action::Defend(attack_card_index, card),
+            None => Action::Take,
+        };
+        Hint::new(action, hand, table)
+    }
+
+    /// Transfer or, if the bot wouldn't transfer, its defense
+    pub fn advise_transfer(&mut self, hand: & [cards::Card], table: & Table, next_defender_cards_count: usize, can_show_trump: bool) -> Hint
+    {
+        *self.bot.cards_mut() = hand.to_vec();
+        match self.bot.play_transfer_card(table, next_defender_cards_count, can_show_trump, &mut self.rng)
+        {
+            Some(Transfer::Card(card)) => Hint::new(Action::Transfer(card), hand, table),
+            Some(Transfer::ShowTrump(card)) => Hint::new(Action::ShowTrump(card), hand, table),
+            None => self.advise_defense(hand, table),
+        }
+    }
+}
+
+impl Default for Advisor
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+/// Main reasons of the move in a few words
+fn rationale(action: Action, hand: & [cards::Card], table: & Table) -> String
+{
+    let trump = table.trump();
+    let is_trump = |card: & cards::Card| card.suit() == trump && !card.is_joker();
+    let has_trumps = hand.iter().any(is_trump);
+    let defenses = table.legal_defenses(hand);
+    let rank = |card: & cards::Card| card.value().to_string().trim().to_string();
+    let name = |card: & cards::Card| card.to_string().trim().to_string();
+    let mut reasons = vec![];
+    match action
+    {
+        Action::Attack(card) =>
+        {
+            let same_value_count = hand.iter().filter(|hand_card| **hand_card != card && hand_card.value() == card.value()).count();
+            let is_lowest = hand.iter().filter(|hand_card| !hand_card.is_joker() && is_trump(hand_card) == is_trump(& card))
+                .all(|hand_card| hand_card.value() >= card.value());
+            if card.is_joker()
+            {
+                reasons.push("nothing else is left to lead".to_string());
+            }
+            else if is_trump(& card)
+            {
+                reasons.push(if hand.iter().all(|hand_card| is_trump(hand_card) || hand_card.is_joker()) {"only trumps are left"} else {"trump attack is hard to beat"}.to_string());
+            }
+            if same_value_count > 0
+            {
+                reasons.push(format!("{same_value_count} more of rank {} to throw in", rank(& card)));
+            }
+            if is_lowest && !card.is_joker()
+            {
+                reasons.push("gets rid of the lowest card".to_string());
+            }
+        },
+        Action::ThrowIn(card) => reasons.push(format!("throw-in matches rank {} on table", rank(& card))),
+        Action::Defend(attack_card_index, card) =>
+        {
+            // a trump beating a plain card or a joker beating anything may have a cheaper alternative
+            let attack_card = table.played_cards()[attack_card_index].0;
+            let kind = if card.is_joker() {"joker"} else {"trump"};
+            let cheaper_defense = defenses.iter()
+                .filter(|(index, defense_card)| *index == attack_card_index && !defense_card.is_joker() && (card.is_joker() || !is_trump(defense_card)))
+                .map(|(_, defense_card)| *defense_card)
+                .min_by_key(|defense_card| defense_card.weight(trump));
+            match cheaper_defense
+            {
+                _ if !card.is_joker() && (!is_trump(& card) || is_trump(& attack_card)) => reasons.push(format!("cheapest card beating the {}", name(& attack_card))),
+                Some(defense_card) => reasons.push(format!("{kind} chosen over the {}", name(& defense_card))),
+                None => reasons.push(format!("only a {kind} beats the {}", name(& attack_card))),
+            }
+        },
+        Action::Transfer(card) => reasons.push(format!("passes the attack on with another {}", rank(& card))),
+        Action::ShowTrump(card) => reasons.push(format!("passes the attack on showing the {} and keeps it", name(& card))),
+        Action::Take if defenses.is_empty() => reasons.push("nothing beats the attack".to_string()),
+        Action::Take => reasons.push("beating would cost too much".to_string()),
+        Action::Pass => reasons.push("keeps the cards for later".to_string()),
+        Action::Accuse(_) => (),
+    }
+
+    let kept_trump = match action
+    {
+        Action::Attack(card) | Action::ThrowIn(card) | Action::Defend(_, card) | Action::Transfer(card) => !is_trump(& card) && !card.is_joker(),
+        Action::Take => !defenses.is_empty(),
+        Action::Pass => true,
+        Action::ShowTrump(_) | Action::Accuse(_) => false,
+    };
+    if has_trumps && kept_trump
+    {
+        reasons.push("saves trump".to_string());
+    }
+    reasons.join(", ")
+}
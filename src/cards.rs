@@ -1,7 +1,10 @@
+use crate::utils::Error;
+
 pub const CARDS_IN_DECK_COUNT: usize = 6;
 pub const CARDS_IN_DECK_COUNT_SINGNED: isize = 6;
+pub const FIRST_BOUT_CARDS_COUNT: usize = 5;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Value
 {
     Two = 2,
@@ -20,11 +23,13 @@ pub enum Value
     Joker,
 }
 
-impl Value
+impl TryFrom<usize> for Value
 {
-    pub fn from_usize(number: usize) -> Value
+    type Error = Error;
+
+    fn try_from(number: usize) -> Result<Self, Self::Error>
     {
-        match number
+        Ok(match number
         {
              2 => Value::Two,
              3 => Value::Three,
@@ -40,8 +45,8 @@ impl Value
             13 => Value::King,
             14 => Value::Ace,
             15 => Value::Joker,
-            _ => panic!("Can't create card value from number '{}'", number),
-        }
+            _ => return Err(Error::InvalidCardValue(number)),
+        })
     }
 }
 
@@ -69,7 +74,7 @@ impl std::fmt::Display for Value
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Suit
 {
     Club,
@@ -89,15 +94,21 @@ impl Suit
         }
     }
 
-    pub fn from_usize(number: usize) -> Suit
+}
+
+impl TryFrom<usize> for Suit
+{
+    type Error = Error;
+
+    fn try_from(number: usize) -> Result<Self, Self::Error>
     {
         match number
         {
-            0 => Self::Club,
-            1 => Self::Spade,
-            2 => Self::Heart,
-            3 => Self::Diamond,
-            _ => panic!("Can't create card suit from number '{}'", number),
+            0 => Ok(Self::Club),
+            1 => Ok(Self::Spade),
+            2 => Ok(Self::Heart),
+            3 => Ok(Self::Diamond),
+            _ => Err(Error::InvalidCardSuit(number)),
         }
     }
 }
@@ -116,7 +127,28 @@ impl std::fmt::Display for Suit
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JokerRule
+{
+    /// Joker beats any card of its color, trumps included
+    Color,
+    /// Joker beats any card
+    Any,
+}
+
+impl std::fmt::Display for JokerRule
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        write!(f, "{}", match self 
+            {
+                Self::Color => "jokers beat cards of their color",
+                Self::Any   => "jokers beat any card",
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Card
 {
     value : Value,
@@ -139,6 +171,51 @@ impl Card
     {
         self.suit
     }
+
+    /// Red joker has heart suit, black joker has spade suit
+    pub fn joker(is_red: bool) -> Self
+    {
+        Self::new(Value::Joker, if is_red {Suit::Heart} else {Suit::Spade})
+    }
+
+    pub fn is_joker(& self) -> bool
+    {
+        self.value == Value::Joker
+    }
+
+    /// Rough strength of the card: any trump is stronger than plain cards and jokers are the strongest
+    pub fn weight(& self, trump: Suit) -> usize
+    {
+        let ace = Value::Ace as usize;
+        if self.is_joker()
+        {
+            self.value as usize + 2 * ace
+        }
+        else if self.suit == trump
+        {
+            self.value as usize + ace
+        }
+        else
+        {
+            self.value as usize
+        }
+    }
+
+    /// Jokers don't depend on trump and can be beaten only by the other joker
+    pub fn joker_beats(& self, other: & Card, joker_rule: JokerRule) -> bool
+    {
+        debug_assert!(self.is_joker());
+        if other.is_joker()
+        {
+            return true;
+        }
+
+        match joker_rule
+        {
+            JokerRule::Color => self.suit.same_color_as(other.suit),
+            JokerRule::Any   => true,
+        }
+    }
 }
 
 impl std::fmt::Display for Card
@@ -155,6 +232,14 @@ impl std::fmt::Display for Card
     }
 }
 
+impl PartialOrd for Card
+{
+    fn partial_cmp(& self, other: & Self) -> Option<std::cmp::Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for Card
 {
     fn cmp(& self, other: & Self) -> std::cmp::Ordering
@@ -166,7 +251,46 @@ impl Ord for Card
     }
 }
 
-#[derive(Clone, Copy)]
+/// Reads cards like '6S', '10H', 'QC' or 'A♦', jokers are 'RJ' and 'BJ'
+impl std::str::FromStr for Card
+{
+    type Err = Error;
+
+    fn from_str(text: & str) -> Result<Self, Self::Err>
+    {
+        let invalid_card = || Error::InvalidCard(text.to_string());
+        match text.to_uppercase().as_str()
+        {
+            "RJ" => return Ok(Card::joker(true)),
+            "BJ" => return Ok(Card::joker(false)),
+            _ => (),
+        }
+
+        let suit_char = text.chars().last().ok_or_else(invalid_card)?;
+        let suit = match suit_char.to_ascii_uppercase()
+        {
+            'C' | '♣' => Suit::Club,
+            'S' | '♠' => Suit::Spade,
+            'H' | '♥' => Suit::Heart,
+            'D' | '♦' => Suit::Diamond,
+            _ => return Err(invalid_card()),
+        };
+        let value = match text[.. text.len() - suit_char.len_utf8()].to_uppercase().as_str()
+        {
+            "J" => Value::Jack,
+            "Q" => Value::Queen,
+            "K" => Value::King,
+            "A" => Value::Ace,
+            number => number.parse::<usize>().ok()
+                .filter(|number| (Value::Two as usize ..= Value::Ten as usize).contains(number))
+                .and_then(|number| Value::try_from(number).ok())
+                .ok_or_else(invalid_card)?,
+        };
+        Ok(Card::new(value, suit))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Deck
 {
     Reduced = 24,
@@ -175,6 +299,51 @@ pub enum Deck
     Extended = 54,
 }
 
+impl Deck
+{
+    /// Lowest card value of every suit in the deck
+    pub fn lowest_value(self) -> Value
+    {
+        match self
+        {
+            Deck::Reduced  => Value::Nine,
+            Deck::Standart => Value::Six,
+            Deck::Full | Deck::Extended => Value::Two,
+        }
+    }
+
+    pub fn jokers_count(self) -> usize
+    {
+        match self
+        {
+            Deck::Extended => 2,
+            _ => 0,
+        }
+    }
+
+    /// All cards of the deck in order of values, red and black jokers are the last
+    pub fn cards(self) -> Vec<Card>
+    {
+        let mut cards = Vec::with_capacity(self as usize);
+        let values = (self.lowest_value() as usize ..= Value::Ace as usize)
+            .filter_map(|number| Value::try_from(number).ok());
+        for value in values
+        {
+            cards.push(Card::new(value, Suit::Club));
+            cards.push(Card::new(value, Suit::Spade));
+            cards.push(Card::new(value, Suit::Heart));
+            cards.push(Card::new(value, Suit::Diamond));
+        }
+
+        if self.jokers_count() > 0
+        {
+            cards.push(Card::joker(true));
+            cards.push(Card::joker(false));
+        }
+        cards
+    }
+}
+
 pub fn output_cards(cards: & Vec<Card>)
 {
     for _ in 0..cards.len()
@@ -0,0 +1,133 @@
+use crate::cards;
+use crate::game::{SettingsBuilder, Variant};
+use crate::mcts::{MctsBot, SearchBudget};
+use crate::player::BotDificulty;
+use crate::utils::*;
+
+pub const HELP: &str = "\
+Usage: card-game [OPTIONS]
+
+Options:
+   --deck <24|36|52|54>             Deck size, 54 cards include two jokers [default: 36]
+   --variant <podkidnoy|perevodnoy> Rules variant [default: podkidnoy]
+   --bots <COUNT|DIFFICULTIES>      Number of medium bots or comma separated difficulties
+                                    (easy, medium, hard, expert), e.g. 'easy,hard' [default: easy,medium,hard]
+   --expert-budget <BUDGET>         Thinking time of expert bots and hints per move, e.g. '300ms' or '2s',
+                                    or a plain number of search iterations [default: 1s]
+   --player <NAME>                  Adds a human player, can be repeated
+   --hints <NAME>                   Lets the human player type 'hint' to get advice, can be repeated
+   --seed <NUMBER>                  Seed to reproduce the game
+   --until-one-remains              Play until one player remains instead of until the first win,
+                                    games of three or more players stopped by the first win have no durak
+   --games <COUNT>                  Number of games in the match [default: 1]
+   --solve <POSITION>               Solves the endgame of two players instead of playing, e.g.
+                                    'trump: 9S; attacker: 6H 7C KS; defender: 8H AS; table: 7D/9D JC'
+   -h, --help                       Print this help
+";
+
+/// Game setup given in command-line arguments
+pub struct Config
+{
+    pub settings      : SettingsBuilder,
+    pub bots          : Vec<BotDificulty>,
+    pub expert_budget : SearchBudget,
+    pub players_names : Vec<String>,
+    /// Human players who can ask for hints
+    pub hinted_names  : Vec<String>,
+    pub games_count   : usize,
+    /// Endgame position to solve
+    pub position      : Option<String>,
+    pub show_help     : bool,
+}
+
+impl Config
+{
+    /// Parses arguments without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Error>
+    {
+        let mut config = Self
+        {
+            settings      : SettingsBuilder::new(),
+            bots          : vec![BotDificulty::Easy, BotDificulty::Medium, BotDificulty::Hard],
+            expert_budget : MctsBot::DEFAULT_BUDGET,
+            players_names : vec![],
+            hinted_names  : vec![],
+            games_count   : 1,
+            position      : None,
+            show_help     : false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "-h" | "--help" => config.show_help = true,
+                "--until-one-remains" => config.settings.finish_after_first_win = false,
+                "--deck" | "--variant" | "--bots" | "--expert-budget" | "--player" | "--hints" | "--seed" | "--games" | "--solve" =>
+                {
+                    let value = args.next().ok_or_else(|| Error::MissingArgumentValue(arg.clone()))?;
+                    config.parse_value(& arg, & value)?;
+                },
+                _ => return Err(Error::InvalidArgument(arg)),
+            }
+        }
+
+        if let Some(name) = config.hinted_names.iter().find(|name| !config.players_names.contains(name))
+        {
+            return Err(Error::InvalidArgument(format!("--hints {name}")));
+        }
+        Ok(config)
+    }
+
+    fn parse_value(&mut self, arg: & str, value: & str) -> Result<(), Error>
+    {
+        let invalid_value = || Error::InvalidArgument(format!("{arg} {value}"));
+        match arg
+        {
+            "--deck" =>
+            {
+                self.settings.card_deck = match value
+                {
+                    "24" => cards::Deck::Reduced,
+                    "36" => cards::Deck::Standart,
+                    "52" => cards::Deck::Full,
+                    "54" => cards::Deck::Extended,
+                    _ => return Err(invalid_value()),
+                };
+            },
+            "--variant" =>
+            {
+                self.settings.variant = match value
+                {
+                    "podkidnoy" => Variant::Podkidnoy,
+                    "perevodnoy" => Variant::Perevodnoy,
+                    _ => return Err(invalid_value()),
+                };
+            },
+            "--bots" =>
+                self.bots = match value.parse::<usize>()
+                {
+                    Ok(count) => vec![BotDificulty::Medium; count],
+                    Err(_) => value.split(',')
+                        .map(|difficulty| match difficulty.trim()
+                        {
+                            "easy" => Ok(BotDificulty::Easy),
+                            "medium" => Ok(BotDificulty::Medium),
+                            "hard" => Ok(BotDificulty::Hard),
+                            "expert" => Ok(BotDificulty::Expert),
+                            _ => Err(invalid_value()),
+                        })
+                        .collect::<Result<Vec<BotDificulty>, Error>>()?,
+                },
+            "--expert-budget" => self.expert_budget = value.parse().map_err(|_| invalid_value())?,
+            "--player" => self.players_names.push(value.to_string()),
+            "--hints" => self.hinted_names.push(value.to_string()),
+            "--seed" => self.settings.seed = Some(value.parse().map_err(|_| invalid_value())?),
+            "--games" => self.games_count = value.parse().ok().filter(|count| *count > 0).ok_or_else(invalid_value)?,
+            "--solve" => self.position = Some(value.to_string()),
+            _ => return Err(Error::InvalidArgument(arg.to_string())),
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,83 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 
-use crate::table::Table;
+use crate::table::{Table, Transfer};
 use crate::utils::*;
 use crate::{cards, player};
 use crate::player::Player;
+use crate::state::{Action, CardsSource, Event, Events, GameState, Phase, StartReason};
 
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant
+{
+    /// Attackers can only throw in cards of played values
+    Podkidnoy,
+    /// Defender can also transfer the attack to the next player
+    Perevodnoy,
+}
+
+impl std::fmt::Display for Variant
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        write!(f, "{}", match self 
+            {
+                Self::Podkidnoy  => "podkidnoy",
+                Self::Perevodnoy => "perevodnoy",
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartingRule
+{
+    /// Player with the lowest trump starts
+    LowestTrump,
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThrowInRule
+{
+    /// Every player except the defender
+    Everybody,
+    /// Only the players to the left and to the right of the defender, in team play the nearest opponents of the defender
+    Neighbors,
+    /// Only the player seated before the defender, "one-on-one": the attacker or, after a transfer, the player who transferred.
+    /// In team play every opponent whose next opponent is the defender, so the attacker's partner may be one of them
+    LeftNeighbor,
+}
+
+impl std::fmt::Display for ThrowInRule
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        write!(f, "{}", match self 
+            {
+                Self::Everybody    => "everybody throws in",
+                Self::Neighbors    => "neighbors throw in",
+                Self::LeftNeighbor => "one-on-one",
+            })
+    }
+}
+
 pub struct SettingsBuilder
 {
     pub card_deck              : cards::Deck,
+    pub variant                : Variant,
+    pub trump_show_transfer    : bool,
+    pub first_bout_limit       : bool,
+    pub defender_cards_limit   : bool,
+    pub joker_rule             : cards::JokerRule,
+    pub starting_rule          : StartingRule,
+    pub seed                   : Option<u64>,
+    pub max_illegal_moves      : usize,
     pub cheats_allowed         : bool,
     pub finish_after_first_win : bool,
+    pub defense_after_throw_ins: bool,
+    pub throw_in_rule          : ThrowInRule,
+    pub team_play              : bool,
+    pub hints                  : bool,
 }
 
 impl SettingsBuilder
@@ -20,8 +87,20 @@ impl SettingsBuilder
         Self
         {
             card_deck: cards::Deck::Standart,
+            variant: Variant::Podkidnoy,
+            trump_show_transfer: false,
+            first_bout_limit: false,
+            defender_cards_limit: true,
+            joker_rule: cards::JokerRule::Color,
+            starting_rule: StartingRule::LowestTrump,
+            seed: None,
+            max_illegal_moves: 3,
             cheats_allowed: false,
             finish_after_first_win: true,
+            defense_after_throw_ins: false,
+            throw_in_rule: ThrowInRule::Everybody,
+            team_play: false,
+            hints: false,
         }
     }
     
@@ -31,26 +110,122 @@ impl SettingsBuilder
         self
     }
     
+    pub fn variant(mut self, variant: Variant) -> Self
+    {
+        self.variant = variant;
+        self
+    }
+    
+    /// Only for perevodnoy: defender can transfer attack by showing a trump of the attack value
+    pub fn trump_show_transfer(mut self, trump_show_transfer: bool) -> Self
+    {
+        self.trump_show_transfer = trump_show_transfer;
+        self
+    }
+    
+    /// Maximum 5 attack cards in the first bout of the game
+    pub fn first_bout_limit(mut self, first_bout_limit: bool) -> Self
+    {
+        self.first_bout_limit = first_bout_limit;
+        self
+    }
+    
+    /// Unbeaten attack cards can't outnumber defender's cards
+    pub fn defender_cards_limit(mut self, defender_cards_limit: bool) -> Self
+    {
+        self.defender_cards_limit = defender_cards_limit;
+        self
+    }
+    
+    pub fn joker_rule(mut self, joker_rule: cards::JokerRule) -> Self
+    {
+        self.joker_rule = joker_rule;
+        self
+    }
+    
+    pub fn starting_rule(mut self, starting_rule: StartingRule) -> Self
+    {
+        self.starting_rule = starting_rule;
+        self
+    }
+    
+    /// Same seed and same players' decisions reproduce the same game
+    pub fn seed(mut self, seed: u64) -> Self
+    {
+        self.seed = Some(seed);
+        self
+    }
+    
+    /// Illegal moves in a row after which player forfeits the move (passes, takes or plays his/her first card)
+    pub fn max_illegal_moves(mut self, max_illegal_moves: usize) -> Self
+    {
+        self.max_illegal_moves = max_illegal_moves;
+        self
+    }
+    
     pub fn cheats_allowed(mut self, cheats_allowed: bool) -> Self
     {
         self.cheats_allowed = cheats_allowed;
         self
     }
     
+    /// Game stops as soon as a player gets rid of the cards. With more than two players nobody is left
+    /// the only one with cards then, so such games record the winner but no durak
     pub fn finish_after_first_win(mut self, finish_after_first_win: bool) -> Self
     {
         self.finish_after_first_win = finish_after_first_win;
         self
     }
     
+    /// Attackers throw in cards until everybody passes and only then defender beats all of them or takes
+    pub fn defense_after_throw_ins(mut self, defense_after_throw_ins: bool) -> Self
+    {
+        self.defense_after_throw_ins = defense_after_throw_ins;
+        self
+    }
+    
+    /// Players who are allowed to throw in cards to the defender
+    pub fn throw_in_rule(mut self, throw_in_rule: ThrowInRule) -> Self
+    {
+        self.throw_in_rule = throw_in_rule;
+        self
+    }
+    
+    /// Two teams where partners sit opposite each other (every second player) and the team loses
+    /// if any of its members is the last one holding cards
+    pub fn team_play(mut self, team_play: bool) -> Self
+    {
+        self.team_play = team_play;
+        self
+    }
+    
+    /// Every human player may type 'hint' to get advice, `RealPlayer::advisor` enables hints for one player only
+    pub fn hints(mut self, hints: bool) -> Self
+    {
+        self.hints = hints;
+        self
+    }
+    
     pub fn build(& self) -> Settings
     {
         Settings
         {
             card_deck: self.card_deck,
             max_players_count: self.card_deck as usize / cards::CARDS_IN_DECK_COUNT,
+            variant: self.variant,
+            trump_show_transfer: self.trump_show_transfer,
+            first_bout_limit: self.first_bout_limit,
+            defender_cards_limit: self.defender_cards_limit,
+            joker_rule: self.joker_rule,
+            starting_rule: self.starting_rule,
+            seed: self.seed,
+            max_illegal_moves: self.max_illegal_moves,
             cheats_allowed: self.cheats_allowed,
             finish_after_first_win: self.finish_after_first_win,
+            defense_after_throw_ins: self.defense_after_throw_ins,
+            throw_in_rule: self.throw_in_rule,
+            team_play: self.team_play,
+            hints: self.hints,
         }
     }
 }
@@ -60,33 +235,198 @@ pub struct Settings
 {
     card_deck              : cards::Deck,
     max_players_count      : usize,
+    variant                : Variant,
+    trump_show_transfer    : bool,
+    first_bout_limit       : bool,
+    defender_cards_limit   : bool,
+    joker_rule             : cards::JokerRule,
+    starting_rule          : StartingRule,
+    seed                   : Option<u64>,
+    max_illegal_moves      : usize,
     cheats_allowed         : bool,
     finish_after_first_win : bool,
+    defense_after_throw_ins: bool,
+    throw_in_rule          : ThrowInRule,
+    team_play              : bool,
+    hints                  : bool,
+}
+
+impl Settings
+{
+    pub const TEAMS_COUNT: usize = 2;
+
+    pub fn card_deck(& self) -> cards::Deck
+    {
+        self.card_deck
+    }
+
+    pub fn joker_rule(& self) -> cards::JokerRule
+    {
+        self.joker_rule
+    }
+
+    pub fn first_bout_limit(& self) -> bool
+    {
+        self.first_bout_limit
+    }
+
+    pub fn defender_cards_limit(& self) -> bool
+    {
+        self.defender_cards_limit
+    }
+
+    pub fn cheats_allowed(& self) -> bool
+    {
+        self.cheats_allowed
+    }
+
+    pub fn variant(& self) -> Variant
+    {
+        self.variant
+    }
+
+    pub fn trump_show_transfer(& self) -> bool
+    {
+        self.trump_show_transfer
+    }
+
+    pub fn starting_rule(& self) -> StartingRule
+    {
+        self.starting_rule
+    }
+
+    pub fn finish_after_first_win(& self) -> bool
+    {
+        self.finish_after_first_win
+    }
+
+    pub fn defense_after_throw_ins(& self) -> bool
+    {
+        self.defense_after_throw_ins
+    }
+
+    pub fn throw_in_rule(& self) -> ThrowInRule
+    {
+        self.throw_in_rule
+    }
+
+    pub fn team_play(& self) -> bool
+    {
+        self.team_play
+    }
+
+    pub fn hints(& self) -> bool
+    {
+        self.hints
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PlayerStats
+{
+    pub name                      : String,
+    /// Only in team play
+    pub team                      : Option<usize>,
+    pub attack_cards_count        : usize,
+    pub beaten_cards_count        : usize,
+    pub defenses_count            : usize,
+    pub successful_defenses_count : usize,
+    pub taken_cards_count         : usize,
+    pub caught_cheats_count       : usize,
+    pub false_accusations_count   : usize,
 }
 
-#[derive(Default)]
-struct RoundInfo
+impl PlayerStats
 {
-    is_defense_succeed: bool,
-    first_attacking_index: usize,
-    attacking_index: usize,
-    last_not_passed_index: usize,
-    defending_index: usize,
-    passes_count: usize,
+    fn new(name: & str, team: Option<usize>) -> Self
+    {
+        Self {name: name.to_string(), team, ..Default::default()}
+    }
 }
 
-impl RoundInfo
+#[derive(Clone, Debug)]
+pub struct GameResult
 {
+    /// Player who started the first round
+    pub starting_index  : usize,
+    /// Indices of players in order they got rid of their cards
+    pub finishing_order : Vec<usize>,
+    /// The only player left with cards, `None` when a game of more than two players stops after the first win
+    pub durak           : Option<usize>,
+    /// Last player got rid of the cards with sixes, so the durak wears them as epaulettes
+    pub is_pogony       : bool,
+    /// Only in team play: the team of players left with cards
+    pub losing_team     : Option<usize>,
+    /// Last players got rid of their cards simultaneously or the game got stuck
+    pub is_draw         : bool,
+    pub rounds_count    : usize,
+    /// Indexed the same way as players were added
+    pub players_stats   : Vec<PlayerStats>,
 }
 
+impl std::fmt::Display for GameResult
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        writeln!(f, "Game over after {} rounds", self.rounds_count)?;
+        for (place, player_index) in self.finishing_order.iter().enumerate()
+        {
+            writeln!(f, "{:>3}. {}", place + 1, self.players_stats[*player_index].name)?;
+        }
+
+        match self.durak
+        {
+            Some(player_index) if self.is_pogony => writeln!(f, "{} is the durak with pogony!", self.players_stats[player_index].name)?,
+            Some(player_index) => writeln!(f, "{} is the durak!", self.players_stats[player_index].name)?,
+            None if self.is_draw => writeln!(f, "It's a draw!")?,
+            None => (),
+        }
+
+        if let Some(team) = self.losing_team
+        {
+            let names: Vec<& str> = self.players_stats.iter()
+                .filter(|stats| stats.team == Some(team))
+                .map(|stats| stats.name.as_str())
+                .collect();
+            writeln!(f, "Team {} ({}) lost!", team + 1, names.join(", "))?;
+        }
+
+        writeln!(f, "\n{:<20} {:>7} {:>7} {:>9} {:>7}", "Player", "Attacks", "Beaten", "Defenses", "Taken")?;
+        for stats in self.players_stats.iter()
+        {
+            writeln!(f, "{:<20} {:>7} {:>7} {:>5}/{:<3} {:>7}", stats.name, stats.attack_cards_count, stats.beaten_cards_count,
+                stats.successful_defenses_count, stats.defenses_count, stats.taken_cards_count)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Decision the current player is asked for
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Request
+{
+    Attack,
+    ThrowIn,
+    ThrowInToTake,
+    Transfer,
+    Defense,
+    Challenge,
+}
+
+/// Drives the game state with players' decisions and reports what happens
 pub struct Game
 {
-    table                        : Table,
+    state                        : GameState,
     players                      : Vec<Box<dyn Player>>,
     settings                     : Settings,
-    winners_count                : usize,
-    //first_attacking_player_index : usize,
-    round_info: RoundInfo, 
+    players_stats                : Vec<PlayerStats>,
+    previous_durak_index         : Option<usize>,
+    starting_index               : usize,
+    /// Cards played in the current round and their players
+    round_cards                  : Vec<(usize, cards::Card)>,
+    seed                         : u64,
+    rng                          : GameRng,
 }
 
 impl Game
@@ -95,14 +435,18 @@ impl Game
     
     pub fn new(settings: Settings) -> Self
     {
+        let seed = settings.seed.unwrap_or_else(|| rand::thread_rng().gen());
         Self
         {
-            table                        : Table::new(settings.card_deck),
+            state                        : GameState::new(& settings),
             players                      : vec![],
-            settings                     : settings,
-            winners_count                : 0,
-            //first_attacking_player_index : 0,
-            round_info: Default::default(), 
+            settings,
+            players_stats                : vec![],
+            previous_durak_index         : None,
+            starting_index               : 0,
+            round_cards                  : vec![],
+            seed,
+            rng                          : GameRng::seed_from_u64(seed),
         }
     }
 
@@ -111,6 +455,11 @@ impl Game
         self.settings.max_players_count 
     }
 
+    pub fn seed(& self) -> u64
+    {
+        self.seed
+    }
+
     pub fn players_count(& self) -> usize
     {
         self.players.len()
@@ -124,254 +473,378 @@ impl Game
         }
         else
         {
-            logln!(0, "{} joined the game!\n", (player.name()));
+            // partners sit opposite each other
+            let team = if self.settings.team_play {Some(self.players_count() % Settings::TEAMS_COUNT)} else {None};
+            match team
+            {
+                Some(team) => logln!(0, "{} joined the game in team {}!\n", (player.name()), (team + 1)),
+                None => logln!(0, "{} joined the game!\n", (player.name())),
+            }
+            self.players_stats.push(PlayerStats::new(player.name(), team));
             self.players.push(player);
             Ok(())
         }
     }
 
+    pub fn player(& self, player_index: usize) -> & dyn Player
+    {
+        self.players[player_index].as_ref()
+    }
+
+    pub fn first_attacking_index(& self) -> usize
+    {
+        self.state.first_attacking_index()
+    }
+
     /// # For test only!
     pub fn table(&mut self) -> &mut Table
     {
-        &mut self.table
+        self.state.table_mut()
+    }
+
+    pub fn state(& self) -> & GameState
+    {
+        & self.state
     }
 
     pub fn prepare(&mut self)
     {
-        logln!(0, "Current settings: {} cards, {}, {}\n",
+        logln!(0, "Current settings: {} cards, {}{}, {}, {}, {}\n",
             (self.settings.card_deck as usize),
+            (self.settings.variant),
+            (if self.settings.variant == Variant::Perevodnoy && self.settings.trump_show_transfer {" with trump show"} else {""}),
+            (self.settings.throw_in_rule),
             (if self.settings.cheats_allowed {"cheats are allowed"} else {"cheats are forbiden"}),
             (if self.settings.finish_after_first_win {"playnig until first win"} else {"playing until one player remain"}));
+        if self.settings.card_deck.jokers_count() > 0
+        {
+            logln!(0, "Jokers rule: {}\n", (self.settings.joker_rule));
+        }
+        logln!(0, "Seed: {}\n", (self.seed));
+        let players_count = self.players_count();
+        for (player_index, (player, stats)) in self.players.iter_mut().zip(self.players_stats.iter_mut()).enumerate()
+        {
+            player.cards_mut().clear();
+            player.notice_game_start(player_index, players_count, & self.settings);
+            *stats = PlayerStats::new(player.name(), stats.team);
+        }
+
         logln!(0, "Shufling deck...\n");
-        self.table.reset();
+        let mut serving_events = self.state.deal(self.players_count(), self.previous_durak_index, &mut self.rng);
         
-        logln!(0, "Serving cards...\n");
+        let trump_card = self.state.table().trump_card();
+        logln!(0, "Trump card is the {}\n", trump_card);
         for player in self.players.iter_mut()
         {
-            player.take_cards(&mut self.table.draw_stock_cards(cards::CARDS_IN_DECK_COUNT).unwrap());
+            player.notice_trump_card(trump_card, None);
+        }
+
+        logln!(0, "Serving cards...\n");
+        let first_attacker_position = serving_events.iter()
+            .position(|event| matches!(event, Event::FirstAttackerChosen {..}))
+            .unwrap_or(serving_events.len());
+        let round_events = serving_events.split_off(first_attacker_position);
+        self.handle_events(serving_events);
+        for player in self.players.iter()
+        {
             player.show_cards();
         }
     
         logln!(0, "Choosing starting player...\n");
-        self.round_info.first_attacking_index = rand::thread_rng().gen_range(0..self.players_count());
+        self.handle_events(round_events);
+    }
+
+    /// See `GameState::lowest_trump_holder`
+    pub fn lowest_trump_holder(& self) -> Option<(usize, cards::Card)>
+    {
+        self.state.lowest_trump_holder()
     }
 
-    pub fn start(&mut self)
+    pub fn start(&mut self) -> Result<GameResult, Error>
     {
         if self.players_count() < Self::MIN_PLAYERS_COUNT
         {
-            logln!(0, "There are not enough players in this game to start (need {} more)\n", (Self::MIN_PLAYERS_COUNT - self.players_count()));
-            return;
+            return Err(Error::NotEnoughPlayers(Self::MIN_PLAYERS_COUNT - self.players_count()));
         }
-
-        self.prepare();
-        
-        logln!(0, "Game have started! ══════════════════════\n");
-        if self.settings.finish_after_first_win
+        if self.settings.team_play && self.players_count() % Settings::TEAMS_COUNT != 0
         {
-            while self.winners_count == 0
-            {
-                self.play_round();
-            }
+            return Err(Error::UnevenTeams(self.players_count()));
         }
-        else
+
+        self.prepare();
+        while let Some(player_index) = self.state.current_player()
         {
-            while self.winners_count < self.players_count() - 1
+            match self.state.phase()
             {
-                self.play_round();
-            }
+                Phase::Attack => self.process_player_move(player_index, Request::Attack),
+                Phase::ThrowIn if self.state.is_defender_taking() => self.process_player_move(player_index, Request::ThrowInToTake),
+                Phase::ThrowIn => self.process_player_move(player_index, Request::ThrowIn),
+                Phase::Defense =>
+                    self.state.can_transfer() && self.process_player_move(player_index, Request::Transfer)
+                        || self.process_player_move(player_index, Request::Defense),
+                Phase::Challenge(_) => self.process_player_move(player_index, Request::Challenge),
+                Phase::GameOver => unreachable!(),
+            };
         }
+
+        let result = self.result();
+        self.previous_durak_index = result.durak;
+        logln!(0, "{}", result);
+        Ok(result)
+    }
+
+    /// See `GameState::active_players`
+    pub fn active_players(& self) -> & Vec<usize>
+    {
+        self.state.active_players()
     }
 
-    /// Returns whether player played a card
-    fn process_player_attack(&mut self, is_first_attack: bool) -> bool
+    /// See `GameState::next_active_index`
+    pub fn next_active_index(& self, player_index: usize) -> usize
     {
-        debug_assert!(!self.table.is_attack_finished());
+        self.state.next_active_index(player_index)
+    }
 
-        let player = self.players[self.round_info.attacking_index].as_mut();
-        if player.cards_count() == 0
+    pub fn result(& self) -> GameResult
+    {
+        // the last finished player got rid of only sixes in the final round
+        let is_pogony = self.state.durak().is_some() && self.state.finishing_order().last().is_some_and(|last_index|
         {
-            return false;
+            let mut last_cards = self.round_cards.iter().filter(|(player_index, _)| player_index == last_index).peekable();
+            last_cards.peek().is_some() && last_cards.all(|(_, card)| card.value() == cards::Value::Six)
+        });
+
+        GameResult
+        {
+            starting_index: self.starting_index,
+            finishing_order: self.state.finishing_order().clone(),
+            durak: self.state.durak(),
+            is_pogony,
+            losing_team: self.state.losing_team(),
+            is_draw: self.state.is_draw(),
+            rounds_count: self.state.rounds_count(),
+            players_stats: self.players_stats.clone(),
         }
-        
-        match player.play_attack_card(& self.table, is_first_attack)
+    }
+
+    /// Asks the player for a decision, `None` means player declined to transfer
+    fn ask_player(&mut self, player_index: usize, request: Request) -> Result<Option<Vec<Action>>, Error>
+    {
+        let defender_cards_count = self.state.hand(self.state.defending_index()).len();
+        let player = self.players[player_index].as_mut();
+        let table = self.state.table();
+        let rng = &mut self.rng;
+        let action = match request
         {
-            Some(card) =>
+            Request::Attack =>
             {
-                if is_first_attack
-                {
-                    logln!(0, "{} started attack with the {card}\n", (player.name()));
-                    self.table.take_attack_card(card);
-                    return true;
-                }
-                match self.table.check_attack_card(& card, is_first_attack)
-                {
-                    Ok(_) =>
+                let card = player.play_attack_card(table, true, defender_cards_count, rng).ok_or(Error::AttackNotStarted)?;
+                Some(Action::Attack(card))
+            },
+            Request::ThrowIn => Some(player.play_attack_card(table, false, defender_cards_count, rng).map_or(Action::Pass, Action::ThrowIn)),
+            Request::ThrowInToTake => Some(player.play_card_to_take(table, defender_cards_count, rng).map_or(Action::Pass, Action::ThrowIn)),
+            Request::Transfer =>
+            {
+                let next_defender_cards_count = self.state.hand(self.state.next_defending_index()).len();
+                player.play_transfer_card(table, next_defender_cards_count, self.state.can_show_trump(), rng)
+                    .map(|transfer| match transfer
                     {
-                        logln!(0, "{} continue attack with the {card}\n", (player.name()));
-                        self.table.take_attack_card(card);
-
-                        if !player.has_cards()
-                        {
-                            self.winners_count += 1;
-                            logln!(0, "{} won! ({} winners in total)\n", (player.name()), (self.winners_count));       
-                        }
-                        return true;
-                    },
-                    Err(error) => panic!("{error}"),
-                }
+                        Transfer::Card(card) => Action::Transfer(card),
+                        Transfer::ShowTrump(card) => Action::ShowTrump(card),
+                    })
             },
-            None =>
+            Request::Defense =>
             {
-                logln!(0, "{} passed\n", (player.name()));
-                false
-            }
-        }
-    } 
+                let defenses = match player.play_defense_cards(table, rng)
+                {
+                    Some(defenses) => defenses,
+                    None => return Ok(Some(vec![Action::Take])),
+                };
 
-    /// Returns whether player played a card
-    fn process_player_defense(&mut self) -> bool
-    {
-        let player = self.players[self.round_info.defending_index].as_mut();
-        debug_assert!(player.has_cards());
-        
-        match player.play_defense_card(& self.table)
-        {
-            Some((attack_card_index, defense_card)) =>
-            {
-                logln!(0, "{} beat the {} with the {}\n", (player.name()), (self.table.attack_cards()[attack_card_index]), (defense_card));
-                match self.table.check_defense_card(& defense_card, attack_card_index)
+                // defense can't be partial, otherwise the defender takes
+                if defenses.len() != table.unbeaten_cards_count()
                 {
-                    Ok(_) => 
-                    {
-                        self.table.take_defense_card(defense_card, attack_card_index);
-                        true
-                    },
-                    Err(error) => panic!("{error}"),
+                    return Err(Error::UnbeatenAttackCards(table.unbeaten_cards_count()));
                 }
+                return Ok(Some(defenses.into_iter().map(|(attack_card_index, card)| Action::Defend(attack_card_index, card)).collect()));
             },
-            None => 
-            {
-                logln!(0, "{} is taking the cards\n", (player.name()));
-                false
-            }
-        }
-    } 
+            Request::Challenge => Some(player.challenge_cheat(table, rng).map_or(Action::Pass, Action::Accuse)),
+        };
+        Ok(action.map(|action| vec![action]))
+    }
 
-    fn play_round(&mut self)
+    /// Returns whether an action was applied, player forfeits the move after too many illegal ones
+    fn process_player_move(&mut self, player_index: usize, request: Request) -> bool
     {
-        logln!(0, "New round started! ──────────────────────\n");
-        logln!(0, "{}", (self.table));
-        self.round_info.defending_index = (self.round_info.first_attacking_index + 1) % self.players_count();
-        self.round_info.is_defense_succeed = true;
-
-        // attacking player starts the attack
-        debug_assert!(self.process_player_attack(true), "First attack error");
-
-        if !self.process_player_defense()
+        for _ in 0 ..= self.settings.max_illegal_moves
         {
-            self.round_info.is_defense_succeed = false;
-        }
-        else
-        {
-            debug_assert!(self.players[self.round_info.defending_index].has_cards());
-
-            while !self.table.is_attack_finished()
+            let actions = match self.ask_player(player_index, request)
             {
-                if self.round_info.attacking_index == self.round_info.defending_index
+                Ok(Some(actions)) => actions,
+                Ok(None) => return false,
+                Err(error) =>
                 {
-                    self.round_info.attacking_index = (self.round_info.attacking_index + 1) % self.players_count();
+                    self.reject_move(player_index, error);
                     continue;
-                }
-
-                logln!(0, "{}", (self.table));
-                
-                if self.process_player_attack(false)
-                {   
-                    logln!(0, "{}", (self.table));
-                    self.round_info.last_not_passed_index = self.round_info.attacking_index;
-                    self.round_info.passes_count = 0;
+                },
+            };
 
-                    if !self.process_player_defense() 
-                    {
-                        // player hasn't beaten attacking card
-                        self.round_info.is_defense_succeed = false;
-                        break;
-                    }
-                    else if !self.players[self.round_info.defending_index].has_cards()
-                    {
-                        // player has beaten attacking card with his/her last card
-                        self.round_info.is_defense_succeed = true;
-                        logln!(0, "{} won! ({} winners in total)\n", (self.players[self.round_info.defending_index].name()), (self.winners_count));
-                        self.winners_count += 1;
-                        break;
-                    }
-                }
-                else
+            match self.state.apply_all(& actions)
+            {
+                Ok(events) =>
                 {
-                    self.round_info.passes_count += 1;
-                    if self.round_info.attacking_index == self.round_info.last_not_passed_index
-                    && self.round_info.passes_count >= self.players_count() - 1
-                    {
-                        break;
-                    }
-                    
-                    self.round_info.attacking_index = (self.round_info.attacking_index + 1) % self.players_count();
-                }
+                    self.handle_events(events);
+                    return true;
+                },
+                Err(error) => self.reject_move(player_index, error),
             }
         }
-            
-        if self.round_info.is_defense_succeed
+
+        if request == Request::Transfer
         {
-            logln!(0, "{} beat attack\n", (self.players[self.round_info.defending_index].name()));
+            logln!(0, "{} forfeited the transfer\n", (self.players[player_index].name()));
+            return false;
         }
-        else 
+
+        // attack can't be skipped, so the first card in hand is played instead
+        logln!(0, "{} forfeited the move\n", (self.players[player_index].name()));
+        let action = self.state.forfeit_action().expect("Game isn't over");
+        let events = self.state.apply(action).expect("Forfeit action is always legal");
+        self.handle_events(events);
+        true
+    }
+
+    /// Rejected card is returned to the player who made an illegal move
+    fn reject_move(&mut self, player_index: usize, error: Error)
+    {
+        logln!(0, "{} made an illegal move: {}\n", (self.players[player_index].name()), error);
+        self.sync_hand(player_index);
+        self.players[player_index].notice_illegal_move(& error);
+    }
+
+    /// Game state is the only source of truth about players' cards
+    fn sync_hand(&mut self, player_index: usize)
+    {
+        let hand = self.state.hand(player_index);
+        if self.players[player_index].cards() != hand
         {
-            logln!(0, "{} didn't beat attack\n", (self.players[self.round_info.defending_index].name()));
+            *self.players[player_index].cards_mut() = hand.clone();
         }
+    }
 
-        logln!(0, "{}", (self.table));
-        
-        // attacing players draw cards
-        let mut next_index: usize;
-        let mut player: &mut dyn Player;
-        for i in 0 .. self.players_count()
+    fn handle_events(&mut self, events: Events)
+    {
+        for event in events
         {
-            next_index = (self.round_info.first_attacking_index + i) % self.players_count();
-            if next_index == self.round_info.defending_index
-            {
-                continue;
-            }
-
-            player = self.players[next_index].as_mut();
-            if let Some(mut cards) =
-                self.table.draw_stock_cards(player.missing_cards_count())
-            {
-                player.take_cards(&mut cards);
-            }
+            self.handle_event(event);
         }
 
-        // defending player draws cards
-        let defending_player = self.players[self.round_info.defending_index].as_mut();
-        if self.round_info.is_defense_succeed
+        for player_index in 0 .. self.players_count()
         {
-            self.table.discard_cards();
-            if let Some(mut cards) = 
-                self.table.draw_stock_cards(defending_player.missing_cards_count())
-            {
-                defending_player.take_cards(&mut cards);
-            }
-
-            // choose next player
-            self.round_info.first_attacking_index = self.round_info.defending_index;
+            self.sync_hand(player_index);
         }
-        else 
+    }
+
+    fn handle_event(&mut self, event: Event)
+    {
+        for player in self.players.iter_mut()
         {
-            defending_player.take_cards(&mut self.table.draw_played_cards());
+            player.notice_event(& event);
+        }
 
-            // choose next player
-            self.round_info.first_attacking_index = (self.round_info.defending_index + 1) % self.players_count();
+        match event
+        {
+            Event::FirstAttackerChosen {player_index, reason} =>
+            {
+                match reason
+                {
+                    StartReason::AfterDurak(durak_index) => logln!(0, "{} was the durak in the previous game\n", (self.players[durak_index].name())),
+                    StartReason::LowestTrump(trump) => logln!(0, "{} shows the lowest trump {}\n", (self.players[player_index].name()), trump),
+                    StartReason::NoTrumps => logln!(0, "Nobody has trumps, starting player is chosen randomly\n"),
+                    StartReason::Random => (),
+                }
+                logln!(0, "{} starts the game\n", (self.players[player_index].name()));
+                self.starting_index = player_index;
+                logln!(0, "Game have started! ══════════════════════\n");
+            },
+            Event::RoundStarted {..} =>
+            {
+                self.round_cards.clear();
+                logln!(0, "New round started! ──────────────────────\n");
+                logln!(0, "{}", (self.state.table()));
+            },
+            Event::Attacked {player_index, card} =>
+            {
+                logln!(0, "{} started attack with the {}\n", (self.players[player_index].name()), card);
+                self.players_stats[player_index].attack_cards_count += 1;
+                self.round_cards.push((player_index, card));
+            },
+            Event::ThrewIn {player_index, card} =>
+            {
+                logln!(0, "{} continue attack with the {}\n", (self.players[player_index].name()), card);
+                self.players_stats[player_index].attack_cards_count += 1;
+                self.round_cards.push((player_index, card));
+            },
+            Event::Transferred {player_index, card, ..} =>
+            {
+                logln!(0, "{} transfered attack with the {}\n", (self.players[player_index].name()), card);
+                self.players_stats[player_index].attack_cards_count += 1;
+                self.round_cards.push((player_index, card));
+            },
+            Event::TrumpShown {player_index, card, ..} => logln!(0, "{} transfered attack by showing the {}\n", (self.players[player_index].name()), card),
+            Event::Defended {player_index, attack_card, defense_card} =>
+            {
+                logln!(0, "{} beat the {} with the {}\n", (self.players[player_index].name()), attack_card, defense_card);
+                self.players_stats[player_index].beaten_cards_count += 1;
+                self.round_cards.push((player_index, defense_card));
+            },
+            Event::Passed {player_index} => logln!(0, "{} passed\n", (self.players[player_index].name())),
+            Event::Took {player_index} => logln!(0, "{} is taking the cards\n", (self.players[player_index].name())),
+            Event::CheatCaught {accuser_index, cheater_index, card} =>
+            {
+                logln!(0, "{} caught {} cheating with the {}!\n", (self.players[accuser_index].name()), (self.players[cheater_index].name()), card);
+                self.players_stats[cheater_index].caught_cheats_count += 1;
+            },
+            Event::FalseAccusation {accuser_index, card} =>
+            {
+                logln!(0, "{} falsely accused the {} of cheating\n", (self.players[accuser_index].name()), card);
+                self.players_stats[accuser_index].false_accusations_count += 1;
+            },
+            Event::BoutEnded {defending_index, is_defense_succeed} =>
+            {
+                let defender_stats = &mut self.players_stats[defending_index];
+                defender_stats.defenses_count += 1;
+                if is_defense_succeed
+                {
+                    defender_stats.successful_defenses_count += 1;
+                    logln!(0, "{} beat attack\n", (self.players[defending_index].name()));
+                }
+                else 
+                {
+                    logln!(0, "{} didn't beat attack\n", (self.players[defending_index].name()));
+                }
+                logln!(0, "{}", (self.state.table()));
+            },
+            Event::CardsTaken {player_index, cards, source} =>
+            {
+                if source == CardsSource::Table
+                {
+                    self.players_stats[player_index].taken_cards_count += cards.len();
+                }
+                self.players[player_index].take_cards(&mut cards.into_iter());
+            },
+            Event::TrumpCardDrawn {player_index, trump_card} =>
+            {
+                let name = self.players[player_index].name().to_string();
+                logln!(0, "{} took the trump card {}\n", name, trump_card);
+                for player in self.players.iter_mut()
+                {
+                    player.notice_trump_card(trump_card, Some(& name));
+                }
+            },
+            Event::PlayerFinished {player_index} =>
+                logln!(0, "{} got rid of all cards! ({} players finished in total)\n", (self.players[player_index].name()), (self.state.finishing_order().len())),
+            Event::GameOver => (),
         }
     }
-
-}
\ No newline at end of file
+}
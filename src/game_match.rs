@@ -0,0 +1,110 @@
+use crate::game::{Game, GameResult};
+use crate::utils::*;
+
+/// Cumulative results of a player in the match
+#[derive(Clone, Debug, Default)]
+pub struct Score
+{
+    pub name         : String,
+    /// Games where the player got rid of the cards first
+    pub wins_count   : usize,
+    pub duraks_count : usize,
+    /// Games lost with sixes on the shoulders
+    pub pogony_count : usize,
+}
+
+impl Score
+{
+    fn new(name: & str) -> Self
+    {
+        Self {name: name.to_string(), ..Default::default()}
+    }
+}
+
+/// Series of games with the same players, the player after the previous durak starts the next game
+pub struct Match
+{
+    game            : Game,
+    max_games_count : usize,
+    duraks_limit    : Option<usize>,
+    scores          : Vec<Score>,
+    results         : Vec<GameResult>,
+}
+
+impl Match
+{
+    pub fn new(game: Game, max_games_count: usize) -> Self
+    {
+        Self {game, max_games_count, duraks_limit: None, scores: vec![], results: vec![]}
+    }
+
+    /// Match ends earlier when a player becomes the durak given number of times
+    pub fn duraks_limit(mut self, duraks_limit: usize) -> Self
+    {
+        self.duraks_limit = Some(duraks_limit);
+        self
+    }
+
+    pub fn game(& self) -> & Game
+    {
+        & self.game
+    }
+
+    pub fn scores(& self) -> & Vec<Score>
+    {
+        & self.scores
+    }
+
+    pub fn results(& self) -> & Vec<GameResult>
+    {
+        & self.results
+    }
+
+    pub fn is_over(& self) -> bool
+    {
+        self.results.len() >= self.max_games_count
+            || self.duraks_limit.is_some_and(|limit| self.scores.iter().any(|score| score.duraks_count >= limit))
+    }
+
+    pub fn start(&mut self) -> Result<Vec<Score>, Error>
+    {
+        self.scores = (0 .. self.game.players_count()).map(|player_index| Score::new(self.game.player(player_index).name())).collect();
+        self.results.clear();
+
+        while !self.is_over()
+        {
+            logln!(0, "Game #{} of the match ══════════════════════\n", (self.results.len() + 1));
+            let result = self.game.start()?;
+            // nobody wins a stalemate, even if somebody got rid of the cards before it
+            if let Some(winner_index) = result.finishing_order.first().filter(|_| !result.is_draw)
+            {
+                self.scores[*winner_index].wins_count += 1;
+            }
+            if let Some(durak_index) = result.durak
+            {
+                self.scores[durak_index].duraks_count += 1;
+                if result.is_pogony
+                {
+                    self.scores[durak_index].pogony_count += 1;
+                }
+            }
+            self.results.push(result);
+            logln!(0, "{}", self);
+        }
+        Ok(self.scores.clone())
+    }
+}
+
+impl std::fmt::Display for Match
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        writeln!(f, "Match score after {} games", self.results.len())?;
+        writeln!(f, "{:<20} {:>5} {:>7} {:>7}", "Player", "Wins", "Duraks", "Pogony")?;
+        for score in self.scores.iter()
+        {
+            writeln!(f, "{:<20} {:>5} {:>7} {:>7}", score.name, score.wins_count, score.duraks_count, score.pogony_count)?;
+        }
+        Ok(())
+    }
+}
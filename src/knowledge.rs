@@ -0,0 +1,266 @@
+use crate::cards;
+use crate::game::Settings;
+use crate::state::{CardsSource, Event, GameState, Phase, RoundPosition};
+use crate::table::Table;
+
+/// Cards a player can keep track of from public events: the discard pile, cards opponents picked up
+/// from the table or showed, the trump card and the rest which are either in the stock or in opponents' hands
+#[derive(Clone, Debug, Default)]
+pub struct Knowledge
+{
+    player_index         : usize,
+    trump_card           : Option<cards::Card>,
+    is_trump_card_drawn  : bool,
+    discarded_cards      : Vec<cards::Card>,
+    /// Cards of the current bout
+    table_cards          : Vec<cards::Card>,
+    /// Cards which are surely in every player's hand, all of them for the player himself/herself
+    known_cards          : Vec<Vec<cards::Card>>,
+    hand_sizes           : Vec<usize>,
+    unknown_cards        : Vec<cards::Card>,
+    round                : Option<RoundPosition>,
+    finishing_order      : Vec<usize>,
+}
+
+impl Knowledge
+{
+    pub fn new(player_index: usize, players_count: usize, card_deck: cards::Deck) -> Self
+    {
+        Self
+        {
+            player_index,
+            known_cards   : vec![vec![]; players_count],
+            hand_sizes    : vec![0; players_count],
+            unknown_cards : card_deck.cards(),
+            ..Default::default()
+        }
+    }
+
+    // --- getters ---
+
+    pub fn player_index(& self) -> usize
+    {
+        self.player_index
+    }
+
+    pub fn players_count(& self) -> usize
+    {
+        self.hand_sizes.len()
+    }
+
+    pub fn trump_card(& self) -> Option<cards::Card>
+    {
+        self.trump_card
+    }
+
+    pub fn discarded_cards(& self) -> & Vec<cards::Card>
+    {
+        & self.discarded_cards
+    }
+
+    pub fn known_cards(& self, player_index: usize) -> & Vec<cards::Card>
+    {
+        & self.known_cards[player_index]
+    }
+
+    pub fn hand_size(& self, player_index: usize) -> usize
+    {
+        self.hand_sizes[player_index]
+    }
+
+    /// Cards which aren't known to be anywhere, the trump card lying in the stock isn't among them
+    pub fn unknown_cards(& self) -> & Vec<cards::Card>
+    {
+        & self.unknown_cards
+    }
+
+    /// Defender of the current bout
+    pub fn defending_index(& self) -> Option<usize>
+    {
+        self.round.map(|round| round.defending_index)
+    }
+
+    /// Current round, its attacking index is the last player who attacked
+    pub fn round(& self) -> Option<RoundPosition>
+    {
+        self.round
+    }
+
+    pub fn finishing_order(& self) -> & Vec<usize>
+    {
+        & self.finishing_order
+    }
+
+    /// Cards which may be in opponents' hands, paired with whether they are surely there
+    pub fn opponents_cards(& self) -> Vec<(cards::Card, bool)>
+    {
+        let known_cards = (0 .. self.players_count())
+            .filter(|player_index| *player_index != self.player_index)
+            .flat_map(|player_index| self.known_cards[player_index].iter().map(|card| (*card, true)));
+        known_cards.chain(self.unknown_cards.iter().map(|card| (*card, false))).collect()
+    }
+
+    /// Cards the player may have: known ones and, if his/her hand isn't fully known, unknown ones
+    pub fn possible_cards(& self, player_index: usize) -> Vec<cards::Card>
+    {
+        let mut cards = self.known_cards[player_index].clone();
+        if cards.len() < self.hand_sizes[player_index]
+        {
+            cards.extend(self.unknown_cards.iter().copied());
+        }
+        cards
+    }
+
+    /// When the stock is over and only one opponent may have unknown cards, the game has perfect information
+    pub fn is_everything_known(& self, table: & Table) -> bool
+    {
+        let hidden_hands_count = (0 .. self.players_count())
+            .filter(|player_index| *player_index != self.player_index && self.hand_sizes[*player_index] > self.known_cards[*player_index].len())
+            .count();
+        table.remain_cards_count() == 0 && hidden_hands_count <= 1
+    }
+
+    /// State of the game at the player's decision. Hidden cards are dealt out of the given unknown cards in order:
+    /// opponents get them first and the rest lies in the stock. `None` if the player hasn't followed the game from the start
+    pub fn state(& self, settings: & Settings, hand: & [cards::Card], table: & Table, phase: Phase, mut unknown_cards: Vec<cards::Card>) -> Option<GameState>
+    {
+        let mut round = self.round.filter(|_| self.player_index < self.players_count())?;
+        match phase
+        {
+            Phase::Defense => round.defending_index = self.player_index,
+            _ => round.attacking_index = self.player_index,
+        }
+
+        let hands = (0 .. self.players_count())
+            .map(|player_index|
+            {
+                if player_index == self.player_index
+                {
+                    return hand.to_vec();
+                }
+                let mut hand = self.known_cards[player_index].clone();
+                let unknown_cards_count = (self.hand_sizes[player_index].saturating_sub(hand.len())).min(unknown_cards.len());
+                hand.extend(unknown_cards.drain(.. unknown_cards_count));
+                hand.sort();
+                hand
+            })
+            .collect();
+
+        let mut table = table.clone();
+        if table.remain_cards_count() > 0
+        {
+            table.replace_stock(unknown_cards);
+        }
+        Some(GameState::from_position(settings, table, hands, self.finishing_order.clone(), phase, round))
+    }
+
+    // --- updates ---
+
+    /// Trump card lies face up at the bottom of the stock until somebody draws it
+    pub fn notice_trump_card(&mut self, trump_card: cards::Card)
+    {
+        self.trump_card = Some(trump_card);
+        if !self.is_trump_card_drawn
+        {
+            self.unknown_cards.retain(|card| *card != trump_card);
+        }
+    }
+
+    pub fn notice_event(&mut self, event: & Event)
+    {
+        match event
+        {
+            Event::RoundStarted {attacking_index, defending_index} => self.round = Some(RoundPosition
+                {
+                    first_attacking_index : *attacking_index,
+                    attacking_index       : *attacking_index,
+                    defending_index       : *defending_index,
+                    ..Default::default()
+                }),
+            Event::Attacked {player_index, card} | Event::ThrewIn {player_index, card} =>
+            {
+                self.play_card(*player_index, *card);
+                if let Some(round) = self.round.as_mut()
+                {
+                    round.attacking_index = *player_index;
+                }
+            },
+            Event::Defended {player_index, defense_card, ..} => self.play_card(*player_index, *defense_card),
+            Event::Transferred {player_index, card, defending_index} =>
+            {
+                self.play_card(*player_index, *card);
+                self.transfer(*defending_index);
+            },
+            Event::TrumpShown {player_index, card, defending_index} =>
+            {
+                self.reveal_card(*player_index, *card);
+                self.transfer(*defending_index);
+                if let Some(round) = self.round.as_mut()
+                {
+                    round.is_trump_shown = true;
+                }
+            },
+            Event::Took {..} =>
+            {
+                if let Some(round) = self.round.as_mut()
+                {
+                    round.is_defender_taking = true;
+                }
+            },
+            Event::PlayerFinished {player_index} => self.finishing_order.push(*player_index),
+            Event::BoutEnded {is_defense_succeed: true, ..} => self.discarded_cards.append(&mut self.table_cards),
+            Event::CardsTaken {player_index, cards, source} =>
+            {
+                self.hand_sizes[*player_index] += cards.len();
+                for card in cards
+                {
+                    match source
+                    {
+                        CardsSource::Table => self.table_cards.retain(|table_card| table_card != card),
+                        // penalty cards are the last discarded ones, so everybody knows them
+                        CardsSource::Discard => self.discarded_cards.retain(|discarded_card| discarded_card != card),
+                        // cards drawn by others stay unknown
+                        CardsSource::Stock if *player_index != self.player_index => continue,
+                        CardsSource::Stock => self.unknown_cards.retain(|unknown_card| unknown_card != card),
+                    }
+                    self.known_cards[*player_index].push(*card);
+                }
+            },
+            Event::TrumpCardDrawn {player_index, trump_card} =>
+            {
+                self.is_trump_card_drawn = true;
+                if *player_index != self.player_index
+                {
+                    self.known_cards[*player_index].push(*trump_card);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn transfer(&mut self, defending_index: usize)
+    {
+        if let Some(round) = self.round.as_mut()
+        {
+            round.defending_index = defending_index;
+        }
+    }
+
+    fn play_card(&mut self, player_index: usize, card: cards::Card)
+    {
+        self.hand_sizes[player_index] -= 1;
+        self.known_cards[player_index].retain(|known_card| *known_card != card);
+        self.unknown_cards.retain(|unknown_card| *unknown_card != card);
+        self.table_cards.push(card);
+    }
+
+    /// Card stays in the hand, but everybody has seen it
+    fn reveal_card(&mut self, player_index: usize, card: cards::Card)
+    {
+        if let Some(index) = self.unknown_cards.iter().position(|unknown_card| *unknown_card == card)
+        {
+            self.unknown_cards.remove(index);
+            self.known_cards[player_index].push(card);
+        }
+    }
+}
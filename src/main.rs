@@ -2,10 +2,17 @@
 #![windows_subsystem = "console"]
 
 mod utils;
+mod cli;
 mod cards;
 mod player;
 mod table;
 mod game;
+mod game_match;
+mod state;
+mod knowledge;
+mod mcts;
+mod solver;
+mod advisor;
 
 use crate::cards::{Card, Deck, Value, Suit, CARDS_IN_DECK_COUNT, output_cards};
 use crate::utils::*;
@@ -13,13 +20,97 @@ use crate::player::{BotDificulty, Bot, RealPlayer, Player};
 use crate::game::{Game, SettingsBuilder};
 use crate::table::Table;
 
+fn main() {
+    let config = match cli::Config::parse(std::env::args().skip(1))
+    {
+        Ok(config) => config,
+        Err(error) =>
+        {
+            logln!(0, "{}\n", error);
+            return;
+        },
+    };
+    if config.show_help
+    {
+        print!("{}", cli::HELP);
+        return;
+    }
+    if let Some(position) = config.position.as_deref()
+    {
+        solve_position(position, & config.settings.build());
+        return;
+    }
+
+    let mut game = game::Game::new(config.settings.build());
+    let players = config.players_names.iter()
+        .map(|name|
+        {
+            let player = player::RealPlayer::new(name);
+            let player = if config.hinted_names.contains(name) {player.advisor(advisor::Advisor::new().budget(config.expert_budget))} else {player};
+            Box::new(player) as Box<dyn Player>
+        })
+        .chain(config.bots.iter().map(|difficulty| player::new_bot(*difficulty, config.expert_budget)));
+    for player in players
+    {
+        if let Err(error) = game.add_player(player)
+        {
+            logln!(0, "{}\n", error);
+            return;
+        }
+    }
+
+    let mut game_match = game_match::Match::new(game, config.games_count);
+    if let Err(error) = game_match.start()
+    {
+        logln!(0, "{}\n", error);
+    }
+}
+
+/// Prints the outcome of the endgame for the player to move and his/her best move
+fn solve_position(position: & str, settings: & game::Settings)
+{
+    let solution = solver::parse_position(position, settings)
+        .and_then(|state|
+        {
+            let player_index = state.current_player().ok_or(Error::NotEndgame)?;
+            solver::Solver::new().solve(& state, player_index).map(|solution| (player_index, solution))
+        });
+    match solution
+    {
+        Ok((player_index, solution)) =>
+        {
+            let player = if player_index == 0 {"Attacker"} else {"Defender"};
+            match solution.best_action
+            {
+                Some(action) => logln!(0, "{} {}, best move: {}", player, (solution.outcome), action),
+                None => logln!(0, "{} {}", player, (solution.outcome)),
+            }
+        },
+        Err(error) => logln!(0, "{}\n", error),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::cards::{Card, Deck, Value, Suit, CARDS_IN_DECK_COUNT, output_cards};
-    use crate::player::{BotDificulty, Bot, RealPlayer, Player};
-    use crate::game::{Game, SettingsBuilder};
-    use crate::table::Table;
+    use crate::cards::{Card, Deck, JokerRule, Value, Suit, CARDS_IN_DECK_COUNT, output_cards};
+    use crate::player::{new_bot, BotDificulty, Bot, RealPlayer, Player};
+    use crate::game::{Game, GameResult, Settings, SettingsBuilder, StartingRule, ThrowInRule, Variant};
+    use crate::game_match::Match;
+    use crate::cli::Config;
+    use crate::table::{Table, Transfer};
+    use crate::state::{Action, CardsSource, Event, GameState, Phase, RoundPosition};
+    use crate::knowledge::Knowledge;
+    use crate::mcts::{MctsBot, SearchBudget};
+    use crate::solver::{parse_position, Outcome, Solver};
+    use crate::advisor::{Advisor, Hint};
+    use crate::utils::{Error, GameRng};
+    use std::rc::Rc;
+    use std::cell::{Cell, RefCell};
+    use rand::{Rng, SeedableRng};
+    use rand::seq::SliceRandom;
+    use itertools::Itertools;
+    use std::time::Duration;
  
     fn add_players_to_game(card_deck: Deck)
     {
@@ -52,7 +143,7 @@ mod tests {
         let mut player = RealPlayer::new("foo");
         let mut cards = vec![Card::new(Value::Seven, Suit::Heart), Card::new(Value::Seven, Suit::Spade), Card::new(Value::Seven, Suit::Diamond), Card::new(Value::Seven, Suit::Club)];
         player.take_cards(&mut cards.into_iter());
-        let mut table = Table::new(Deck::Standart);
+        let mut table = Table::new(& SettingsBuilder::new().build());
         
         assert_eq!(player.play_attack_card(& table), None); // pass
         assert_eq!(player.play_attack_card(& table), Some(Card::new(Value::Seven, Suit::Heart))); // 0
@@ -64,7 +155,7 @@ mod tests {
     fn draw_stock_cards()
     {
         let mut game = Game::new(SettingsBuilder::new().build());
-        game.table().reset();
+        game.table().reset(&mut GameRng::seed_from_u64(0));
         let mut bot = Bot::new(BotDificulty::Easy);
 
         bot.take_cards(&mut game.table().draw_stock_cards(6).unwrap());
@@ -75,7 +166,7 @@ mod tests {
     fn draw_played_cards()
     {
         let mut game = Game::new(SettingsBuilder::new().build());
-        game.table().reset();
+        game.table().reset(&mut GameRng::seed_from_u64(0));
         let mut bot = Bot::new(BotDificulty::Easy);
 
         let mut cards = vec![Card::new(Value::Seven, Suit::Heart), Card::new(Value::Seven, Suit::Spade), Card::new(Value::Seven, Suit::Diamond), Card::new(Value::Seven, Suit::Club)];
@@ -84,31 +175,1247 @@ mod tests {
         game.table().take_attack_card(cards.remove(0));
         game.table().take_attack_card(cards.remove(0));
 
-        bot.take_cards(&mut game.table().draw_played_cards());
+        bot.take_cards(&mut game.table().draw_played_cards().unwrap());
         assert_eq!(bot.cards_count(), 4);
     }
-}
 
-fn main() {
-    let mut game = game::Game::new(game::SettingsBuilder::new().build());
+    fn check_deck_composition(card_deck: Deck)
+    {
+        let mut table = Table::new(& SettingsBuilder::new().card_deck(card_deck).build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        assert_eq!(table.remain_cards_count(), card_deck as usize);
 
-    let bot1 = Box::new(player::Bot::new(player::BotDificulty::Easy));
-    bot1.show_cards();
-    let _ = game.add_player(bot1);
+        let cards: Vec<Card> = table.draw_stock_cards(card_deck as usize).unwrap().collect();
+        assert_eq!(cards.len(), card_deck as usize);
 
-    let bot2 = Box::new(player::Bot::new(player::BotDificulty::Medium));
-    bot2.show_cards();
-    let _ = game.add_player(bot2);
-    
-    let bot3 = Box::new(player::Bot::new(player::BotDificulty::Hard));
-    bot3.show_cards();
-    let _ = game.add_player(bot3);
+        for suit in [Suit::Club, Suit::Spade, Suit::Heart, Suit::Diamond]
+        {
+            let suit_cards: Vec<&Card> = cards.iter().filter(|card| card.suit() == suit && card.value() != Value::Joker).collect();
+            assert_eq!(suit_cards.len(), Value::Ace as usize - card_deck.lowest_value() as usize + 1);
+            for value in card_deck.lowest_value() as usize ..= Value::Ace as usize
+            {
+                assert!(suit_cards.contains(&&Card::new(Value::try_from(value).unwrap(), suit)));
+            }
+        }
 
-    let mut player = Box::new(player::RealPlayer::new("FOO"));
-    //let mut cards = vec![Card::new(Value::Seven, Suit::Heart), Card::new(Value::Seven, Suit::Spade), Card::new(Value::Seven, Suit::Diamond), Card::new(Value::Seven, Suit::Club)];
-    //output_cards(&cards);
-    
-    //let _ = game.add_player(player);
+        assert!(cards.iter().all(|card| card.value() >= card_deck.lowest_value()));
+        assert_eq!(cards.iter().filter(|card| card.value() == Value::Joker).count(), card_deck.jokers_count());
+    }
+
+    #[test]
+    fn deck_composition()
+    {
+        check_deck_composition(Deck::Reduced);
+        check_deck_composition(Deck::Standart);
+        check_deck_composition(Deck::Full);
+        check_deck_composition(Deck::Extended);
+
+        assert_eq!(Deck::Reduced.lowest_value(), Value::Nine);
+        assert_eq!(Deck::Standart.lowest_value(), Value::Six);
+        assert_eq!(Deck::Extended.jokers_count(), 2);
+    }
+
+    #[test]
+    fn joker_rules()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().card_deck(Deck::Extended).joker_rule(JokerRule::Color).build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        table.take_attack_card(Card::new(Value::Ace, Suit::Diamond));
+        table.take_attack_card(Card::new(Value::Two, Suit::Club));
+        table.take_attack_card(Card::joker(false));
+
+        // red joker beats only red cards, black joker is beaten only by the other joker
+        assert!(table.can_beat(& Card::joker(true), 0));
+        assert!(!table.can_beat(& Card::joker(true), 1));
+        assert!(table.can_beat(& Card::joker(true), 2));
+        assert!(!table.can_beat(& Card::new(Value::Ace, table.trump()), 2));
+        assert!(!table.can_beat(& Card::new(Value::Ace, Suit::Spade), 2));
+
+        // jokers are thrown in by value like other cards, so only after a joker
+        assert!(table.check_attack_card(& Card::joker(true), false, 6).is_ok());
+        assert!(table.check_attack_card(& Card::new(Value::Five, Suit::Heart), false, 6).is_err());
+        let mut other_table = Table::new(& SettingsBuilder::new().card_deck(Deck::Extended).build());
+        other_table.take_attack_card(Card::new(Value::Two, Suit::Club));
+        assert!(matches!(other_table.check_attack_card(& Card::joker(true), false, 6), Err(Error::AbsentCardValue(Value::Joker))));
+
+        let mut table = Table::new(& SettingsBuilder::new().card_deck(Deck::Extended).joker_rule(JokerRule::Any).build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        table.take_attack_card(Card::new(Value::Two, Suit::Club));
+        assert!(table.can_beat(& Card::joker(true), 0));
+    }
+
+    #[test]
+    fn joker_is_not_trump()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().card_deck(Deck::Extended).build());
+        let mut rng = GameRng::seed_from_u64(0);
+        for _ in 0..100
+        {
+            table.reset(&mut rng);
+            let cards: Vec<Card> = table.draw_stock_cards(Deck::Extended as usize).unwrap().collect();
+            assert!(!cards[0].is_joker());
+            assert_eq!(cards[0].suit(), table.trump());
+        }
+    }
+
+    #[test]
+    fn transfer_attack()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        let trump = table.trump();
+        let suits: Vec<Suit> = [Suit::Club, Suit::Spade, Suit::Heart, Suit::Diamond].into_iter().filter(|suit| *suit != trump).collect();
+
+        table.take_attack_card(Card::new(Value::Seven, suits[0]));
+        assert!(table.check_transfer(& Transfer::Card(Card::new(Value::Seven, suits[1])), 2).is_ok());
+        assert!(matches!(table.check_transfer(& Transfer::Card(Card::new(Value::Seven, suits[1])), 1), Err(Error::NotEnoughCardsToTransfer(1))));
+        assert!(matches!(table.check_transfer(& Transfer::Card(Card::new(Value::Eight, suits[1])), 2), Err(Error::AbsentCardValue(Value::Eight))));
+
+        // shown trump isn't laid on the table
+        assert!(table.check_transfer(& Transfer::ShowTrump(Card::new(Value::Seven, trump)), 1).is_ok());
+        assert!(matches!(table.check_transfer(& Transfer::ShowTrump(Card::new(Value::Seven, suits[1])), 1), Err(Error::InvalidTrumpShow)));
+
+        table.take_defense_card(Card::new(Value::Ace, trump), 0);
+        assert!(matches!(table.check_transfer(& Transfer::Card(Card::new(Value::Seven, suits[1])), 2), Err(Error::TransferAfterDefense)));
+    }
+
+    #[test]
+    fn attack_limits()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().first_bout_limit(true).build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        let sevens = [Card::new(Value::Seven, Suit::Club), Card::new(Value::Seven, Suit::Spade), Card::new(Value::Seven, Suit::Heart), Card::new(Value::Seven, Suit::Diamond)];
+
+        // unbeaten attack cards can't outnumber defender's cards
+        table.take_attack_card(sevens[0]);
+        table.take_attack_card(sevens[1]);
+        assert!(table.is_attack_finished(2));
+        assert!(matches!(table.check_attack_card(& sevens[2], false, 2), Err(Error::NotEnoughDefenderCards(2))));
+        assert!(table.check_attack_card(& sevens[2], false, 3).is_ok());
+
+        // maximum 5 attack cards in the first bout
+        table.take_attack_card(sevens[2]);
+        table.take_attack_card(sevens[3]);
+        table.take_attack_card(Card::new(Value::Seven, Suit::Club));
+        assert!(table.is_attack_finished(6));
+        assert!(matches!(table.check_attack_card(& sevens[0], false, 6), Err(Error::TooManyAttackCards(5))));
+
+        table.discard_cards();
+        assert!(!table.is_first_bout());
+        assert_eq!(table.max_attack_cards_count(), CARDS_IN_DECK_COUNT);
+
+        let mut table = Table::new(& SettingsBuilder::new().defender_cards_limit(false).build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        table.take_attack_card(sevens[0]);
+        table.take_attack_card(sevens[1]);
+        assert!(table.check_attack_card(& sevens[2], false, 1).is_ok());
+    }
+
+    #[test]
+    fn legal_moves()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        let trump = table.trump();
+        let deck: Vec<Card> = table.draw_stock_cards(Deck::Standart as usize).unwrap().collect();
+
+        // any card starts the attack
+        assert_eq!(table.legal_attacks(& deck, CARDS_IN_DECK_COUNT), deck);
+        assert!(table.legal_defenses(& deck).is_empty());
+
+        for attack_card in deck.iter()
+        {
+            table.take_attack_card(*attack_card);
+            let same_value_cards: Vec<Card> = deck.iter().filter(|card| card.value() == attack_card.value()).copied().collect();
+            assert_eq!(table.legal_attacks(& deck, CARDS_IN_DECK_COUNT), same_value_cards);
+            assert!(table.legal_attacks(& deck, 1).is_empty());
+
+            let defenses = table.legal_defenses(& deck);
+            let higher_values_count = Value::Ace as usize - attack_card.value() as usize;
+            let trumps_count = if attack_card.suit() == trump {0} else {Deck::Standart as usize / 4};
+            assert_eq!(defenses.len(), higher_values_count + trumps_count);
+            for (attack_card_index, defense_card) in defenses.iter()
+            {
+                assert_eq!(*attack_card_index, 0);
+                assert!(table.check_defense_card(defense_card, 0).is_ok());
+            }
+
+            // beaten attack can be continued with values of both cards
+            let (_, defense_card) = match defenses.first()
+            {
+                Some(defense) => *defense,
+                None =>
+                {
+                    table.discard_cards();
+                    continue;
+                },
+            };
+            table.take_defense_card(defense_card, 0);
+            assert!(table.legal_defenses(& deck).is_empty());
+            assert!(table.legal_attacks(& deck, CARDS_IN_DECK_COUNT).iter()
+                .all(|card| card.value() == attack_card.value() || card.value() == defense_card.value()));
+            assert_eq!(table.legal_attacks(& deck, CARDS_IN_DECK_COUNT).len(), if defense_card.value() == attack_card.value() {4} else {8});
+            table.discard_cards();
+        }
+
+        // jokers are thrown in only after a joker and only a joker beats a joker
+        let mut table = Table::new(& SettingsBuilder::new().card_deck(Deck::Extended).build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        let deck: Vec<Card> = table.draw_stock_cards(Deck::Extended as usize).unwrap().collect();
+        let jokers: Vec<Card> = deck.iter().filter(|card| card.is_joker()).copied().collect();
+        table.take_attack_card(Card::new(Value::Two, table.trump()));
+        assert!(jokers.iter().all(|joker| !table.legal_attacks(& deck, CARDS_IN_DECK_COUNT).contains(joker)));
+        table.take_defense_card(Card::new(Value::Three, table.trump()), 0);
+        table.take_attack_card(jokers[0]);
+        assert!(table.legal_attacks(& deck, CARDS_IN_DECK_COUNT).contains(& jokers[1]));
+        assert_eq!(table.legal_defenses(& deck), jokers.iter().map(|joker| (1, *joker)).collect::<Vec<(usize, Card)>>());
+    }
+
+    #[test]
+    fn defend_in_any_order()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        let sevens: Vec<Card> = [Suit::Club, Suit::Spade, Suit::Heart, Suit::Diamond].into_iter().filter(|suit| *suit != table.trump())
+            .map(|suit| Card::new(Value::Seven, suit))
+            .collect();
+        for seven in sevens.iter()
+        {
+            table.take_attack_card(*seven);
+        }
+
+        // the last attack card is beaten first and keeps its pairing
+        let last_defense = Card::new(Value::Eight, sevens[2].suit());
+        assert!(table.check_defense_card(& last_defense, 2).is_ok());
+        table.take_defense_card(last_defense, 2);
+        assert!(!table.is_defense_not_started());
+        let first_defense = Card::new(Value::Ace, sevens[0].suit());
+        table.take_defense_card(first_defense, 0);
+        assert_eq!(table.played_cards(), &vec![(sevens[0], Some(first_defense)), (sevens[1], None), (sevens[2], Some(last_defense))]);
+        assert_eq!(table.defense_cards(), vec![first_defense, last_defense]);
+        assert_eq!(table.unbeaten_cards_count(), 1);
+        assert!(!table.is_attack_beaten());
+        assert!(matches!(table.check_defense_card(& Card::new(Value::King, sevens[2].suit()), 2), Err(Error::AttackCardBeaten(2))));
+
+        let hand = vec![Card::new(Value::Nine, sevens[0].suit()), Card::new(Value::Nine, sevens[1].suit())];
+        assert_eq!(table.legal_defenses(& hand), vec![(1, hand[1])]);
+        table.take_defense_card(hand[1], 1);
+        assert!(table.is_attack_beaten());
+        assert!(table.suspicious_cards().is_empty());
+        assert!(table.to_string().contains(& format!(" └┤{} │ └┤{} │ └┤{} │", first_defense, hand[1], last_defense)));
+
+        let mut cards: Vec<Card> = table.draw_played_cards().unwrap().collect();
+        cards.sort();
+        let mut expected_cards = [sevens.clone(), vec![first_defense, hand[1], last_defense]].concat();
+        expected_cards.sort();
+        assert_eq!(cards, expected_cards);
+    }
+
+    #[test]
+    fn trump_card_is_drawn_last()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        let trump_card = table.trump_card();
+        assert_eq!(trump_card.suit(), table.trump());
+        assert!(!table.is_trump_card_drawn());
+
+        let mut cards: Vec<Card> = table.draw_stock_cards(Deck::Standart as usize - 1).unwrap().collect();
+        assert!(!cards.contains(& trump_card));
+        cards.extend(table.draw_stock_cards(1).unwrap());
+        assert_eq!(cards.last(), Some(& trump_card));
+
+        // trump card stays known after it was drawn
+        assert!(table.is_trump_card_drawn());
+        assert_eq!(table.trump_card(), trump_card);
+        assert!(table.to_string().contains(& trump_card.to_string()));
+    }
 
-    game.start();
+    #[test]
+    fn lowest_trump_starts()
+    {
+        for _ in 0..20
+        {
+            let mut game = Game::new(SettingsBuilder::new().starting_rule(StartingRule::LowestTrump).build());
+            for _ in 0..4
+            {
+                assert!(game.add_player(Box::new(Bot::new(BotDificulty::Easy))).is_ok());
+            }
+            game.prepare();
+
+            let trump = game.table().trump();
+            let lowest_trumps: Vec<Option<Value>> = (0..game.players_count())
+                .map(|player_index| game.player(player_index).cards().iter().filter(|card| card.suit() == trump).map(Card::value).min())
+                .collect();
+            match game.lowest_trump_holder()
+            {
+                Some((player_index, card)) =>
+                {
+                    assert_eq!(game.first_attacking_index(), player_index);
+                    assert_eq!(lowest_trumps[player_index], Some(card.value()));
+                    assert!(lowest_trumps.iter().flatten().all(|value| *value >= card.value()));
+                },
+                None => assert!(lowest_trumps.iter().all(Option::is_none)),
+            }
+        }
+    }
+
+    fn quick_bot(difficulty: BotDificulty) -> Box<Bot>
+    {
+        Box::new(Bot::new(difficulty).delay(Duration::ZERO))
+    }
+
+    #[test]
+    fn game_result()
+    {
+        for _ in 0..10
+        {
+            let mut game = Game::new(SettingsBuilder::new().build());
+            assert!(matches!(game.start(), Err(Error::NotEnoughPlayers(2))));
+            assert!(game.add_player(quick_bot(BotDificulty::Easy)).is_ok());
+            assert!(game.add_player(quick_bot(BotDificulty::Hard)).is_ok());
+
+            let result = game.start().unwrap();
+            assert!(result.rounds_count > 0);
+            assert_eq!(result.players_stats.len(), 2);
+            assert!(!result.finishing_order.is_empty());
+            match result.durak
+            {
+                Some(durak) =>
+                {
+                    assert!(!result.is_draw);
+                    assert_eq!(result.finishing_order.len(), 1);
+                    assert!(!result.finishing_order.contains(& durak));
+                    assert!(game.player(durak).has_cards());
+                },
+                None =>
+                {
+                    assert!(result.is_draw);
+                    assert_eq!(result.finishing_order.len(), 2);
+                },
+            }
+
+            let defenses_count: usize = result.players_stats.iter().map(|stats| stats.defenses_count).sum();
+            assert_eq!(defenses_count, result.rounds_count);
+        }
+    }
+
+    fn play_until_one_remains(players_count: usize, variant: Variant)
+    {
+        let mut game = Game::new(SettingsBuilder::new().card_deck(Deck::Extended).variant(variant).finish_after_first_win(false).build());
+        for i in 0..players_count
+        {
+            assert!(game.add_player(quick_bot(if i % 2 == 0 {BotDificulty::Easy} else {BotDificulty::Hard})).is_ok());
+        }
+        game.prepare();
+        assert_eq!(game.active_players(), &(0..players_count).collect::<Vec<usize>>());
+        for player_index in 0..players_count
+        {
+            assert_eq!(game.next_active_index(player_index), (player_index + 1) % players_count);
+        }
+
+        // stuck game is a draw with several players left
+        let result = game.start().unwrap();
+        assert!(game.active_players().len() <= 1 || result.is_draw);
+
+        // every player finished once, everyone but durak got rid of cards
+        let mut finishing_order = result.finishing_order.clone();
+        finishing_order.sort();
+        finishing_order.dedup();
+        assert_eq!(finishing_order.len(), result.finishing_order.len());
+        for player_index in result.finishing_order.iter()
+        {
+            assert!(!game.player(*player_index).has_cards());
+        }
+        match result.durak
+        {
+            Some(durak) =>
+            {
+                assert_eq!(result.finishing_order.len(), players_count - 1);
+                assert_eq!(game.active_players(), &vec![durak]);
+                for player_index in result.finishing_order.iter()
+                {
+                    assert_eq!(game.next_active_index(*player_index), durak);
+                }
+            },
+            None =>
+            {
+                assert!(result.is_draw);
+                assert_eq!(result.finishing_order.len() + game.active_players().len(), players_count);
+            },
+        }
+    }
+
+    #[test]
+    fn finished_players_leave_rotation()
+    {
+        for players_count in 3..=6
+        {
+            for _ in 0..5
+            {
+                play_until_one_remains(players_count, Variant::Podkidnoy);
+                play_until_one_remains(players_count, Variant::Perevodnoy);
+            }
+        }
+    }
+
+    /// Plays as the bot and records every event of the game, including the deal
+    struct RecordingPlayer
+    {
+        bot    : Box<dyn Player>,
+        events : Rc<RefCell<Vec<Event>>>,
+    }
+
+    impl Player for RecordingPlayer
+    {
+        fn cards(& self) -> & Vec<Card> { self.bot.cards() }
+        fn cards_mut(&mut self) -> &mut Vec<Card> { self.bot.cards_mut() }
+        fn name(& self) -> & str { self.bot.name() }
+        fn notice_trump_card(&mut self, trump_card: Card, holder_name: Option<& str>) { self.bot.notice_trump_card(trump_card, holder_name) }
+        fn notice_game_start(&mut self, player_index: usize, players_count: usize, settings: & Settings)
+        {
+            self.bot.notice_game_start(player_index, players_count, settings)
+        }
+        fn notice_event(&mut self, event: & Event)
+        {
+            self.events.borrow_mut().push(event.clone());
+            self.bot.notice_event(event)
+        }
+        fn play_attack_card(&mut self, table: & Table, is_first_attack: bool, defender_cards_count: usize, rng: &mut GameRng) -> Option<Card>
+        {
+            self.bot.play_attack_card(table, is_first_attack, defender_cards_count, rng)
+        }
+        fn play_card_to_take(&mut self, table: & Table, defender_cards_count: usize, rng: &mut GameRng) -> Option<Card>
+        {
+            self.bot.play_card_to_take(table, defender_cards_count, rng)
+        }
+        fn play_defense_card(&mut self, table: & Table, rng: &mut GameRng) -> Option<(usize, Card)>
+        {
+            self.bot.play_defense_card(table, rng)
+        }
+        fn play_transfer_card(&mut self, table: & Table, next_defender_cards_count: usize, can_show_trump: bool, rng: &mut GameRng) -> Option<Transfer>
+        {
+            self.bot.play_transfer_card(table, next_defender_cards_count, can_show_trump, rng)
+        }
+        fn challenge_cheat(&mut self, table: & Table, rng: &mut GameRng) -> Option<Card>
+        {
+            self.bot.challenge_cheat(table, rng)
+        }
+    }
+
+    /// Events of the whole game as the first player saw them
+    fn play_seeded_game(seed: u64) -> (Vec<Event>, GameResult)
+    {
+        let mut game = Game::new(SettingsBuilder::new().seed(seed).finish_after_first_win(false).build());
+        assert_eq!(game.seed(), seed);
+        let events = Rc::new(RefCell::new(vec![]));
+        assert!(game.add_player(Box::new(RecordingPlayer {bot: quick_bot(BotDificulty::Easy), events: Rc::clone(& events)})).is_ok());
+        for difficulty in [BotDificulty::Medium, BotDificulty::Hard]
+        {
+            assert!(game.add_player(quick_bot(difficulty)).is_ok());
+        }
+
+        let result = game.start().unwrap();
+        let events = events.borrow().clone();
+        (events, result)
+    }
+
+    /// Durak counts of two bots in seeded one-on-one games, every deal is played twice with swapped seats
+    fn duel(difficulty: BotDificulty, opponent_difficulty: BotDificulty, deals_count: u64) -> (usize, usize)
+    {
+        let (mut duraks_count, mut opponent_duraks_count) = (0, 0);
+        for (seed, bot_index) in (0..deals_count).cartesian_product(0..2)
+        {
+            let mut game = Game::new(SettingsBuilder::new().seed(seed).build());
+            let difficulties = if bot_index == 0 {[difficulty, opponent_difficulty]} else {[opponent_difficulty, difficulty]};
+            for difficulty in difficulties
+            {
+                let bot = match difficulty
+                {
+                    BotDificulty::Expert => new_bot(difficulty, SearchBudget::Iterations(EXPERT_ITERATIONS_COUNT)),
+                    _ => quick_bot(difficulty),
+                };
+                assert!(game.add_player(bot).is_ok());
+            }
+
+            match game.start().unwrap().durak
+            {
+                Some(durak_index) if durak_index == bot_index => duraks_count += 1,
+                Some(_) => opponent_duraks_count += 1,
+                None => {},
+            }
+        }
+        (duraks_count, opponent_duraks_count)
+    }
+
+    const EXPERT_ITERATIONS_COUNT: usize = 50;
+
+    #[test]
+    fn bot_difficulties()
+    {
+        let (hard_duraks_count, easy_duraks_count) = duel(BotDificulty::Hard, BotDificulty::Easy, 100);
+        assert!(hard_duraks_count * 4 < easy_duraks_count, "Hard is the durak {hard_duraks_count} times, Easy is the durak {easy_duraks_count} times");
+
+        let (hard_duraks_count, medium_duraks_count) = duel(BotDificulty::Hard, BotDificulty::Medium, 200);
+        assert!(hard_duraks_count < medium_duraks_count, "Hard is the durak {hard_duraks_count} times, Medium is the durak {medium_duraks_count} times");
+    }
+
+    #[test]
+    fn expert_bot()
+    {
+        let (expert_duraks_count, easy_duraks_count) = duel(BotDificulty::Expert, BotDificulty::Easy, 10);
+        assert!(expert_duraks_count * 4 < easy_duraks_count, "Expert is the durak {expert_duraks_count} times, Easy is the durak {easy_duraks_count} times");
+
+        // transfers, cheats and teams don't confuse the search
+        for (seed, variant) in (0..4).zip([Variant::Podkidnoy, Variant::Perevodnoy].into_iter().cycle())
+        {
+            let settings = SettingsBuilder::new().seed(seed).variant(variant).trump_show_transfer(true).cheats_allowed(seed < 2)
+                .team_play(seed % 2 == 0).finish_after_first_win(false).build();
+            let mut game = Game::new(settings);
+            for player_index in 0..4
+            {
+                let bot = if player_index == 0 {new_bot(BotDificulty::Expert, SearchBudget::Iterations(10))} else {quick_bot(BotDificulty::Hard)};
+                assert!(game.add_player(bot).is_ok());
+            }
+            let result = game.start().unwrap();
+            assert!(result.durak.is_some() || result.losing_team.is_some() || result.is_draw);
+        }
+
+        // longer thinking draws no more of the game's random numbers
+        let settings = SettingsBuilder::new().build();
+        let state = parse_position("trump: 9S; attacker: 6C 7D; defender: 8H 6D AS; table: 7H", & settings).unwrap();
+        let next_random_numbers: Vec<u64> = [1, 30].into_iter().map(|iterations_count|
+            {
+                let mut bot = new_bot(BotDificulty::Expert, SearchBudget::Iterations(iterations_count));
+                let attack_card = state.table().played_cards()[0].0;
+                bot.notice_game_start(1, 2, & settings);
+                for (player_index, cards) in [state.hand(0).iter().copied().chain([attack_card]).collect(), state.hand(1).clone()].into_iter().enumerate()
+                {
+                    bot.notice_event(& Event::CardsTaken {player_index, cards, source: CardsSource::Stock});
+                }
+                bot.notice_event(& Event::RoundStarted {attacking_index: 0, defending_index: 1});
+                bot.notice_event(& Event::Attacked {player_index: 0, card: attack_card});
+                *bot.cards_mut() = state.hand(1).clone();
+                let mut rng = GameRng::seed_from_u64(0);
+                bot.play_defense_card(state.table(), &mut rng);
+                rng.gen()
+            })
+            .collect();
+        assert_eq!(next_random_numbers[0], next_random_numbers[1]);
+    }
+
+    #[test]
+    fn endgame_solver()
+    {
+        let settings = SettingsBuilder::new().build();
+        let solve = |position: & str, player_index: usize| Solver::new().solve(& parse_position(position, & settings).unwrap(), player_index).unwrap();
+
+        // the trump ace can't be beaten, so the attacker gets rid of the last card
+        let solution = solve("trump: 9S; attacker: AS; defender: 6H 7H", 0);
+        assert_eq!(solution.outcome, Outcome::Win);
+        assert_eq!(solution.best_action, Some(Action::Attack(Card::new(Value::Ace, Suit::Spade))));
+
+        // whatever the attacker leads, the defender beats it and finishes with the trump ace
+        assert_eq!(solve("trump: 9S; attacker: 6H 7C; defender: 8H AS", 0).outcome, Outcome::Loss);
+
+        // the defender must beat the jack with the trump ace, then the seven is thrown in
+        let solution = solve("trump: 9S\nattacker: 6H 7C KS\ndefender: 8H AS\ntable: 7D/9D JC", 1);
+        assert_eq!(solution.outcome, Outcome::Loss);
+        assert_eq!(parse_position("trump: 9S; attacker: 6H; defender: 7H; table: 6D/7D", & settings).unwrap().phase(), Phase::ThrowIn);
+
+        // the attacker has led the last card, so whatever the defender does, the attacker is out
+        let solution = solve("trump: 9S; attacker: ; defender: 8D 6H; table: 7D", 0);
+        assert_eq!(solution.outcome, Outcome::Win);
+
+        // positions which can't occur in a game
+        for position in ["attacker: 6H; defender: 7H", "trump: 9S; dealer: 6H", "trump: 9S; attacker: 6H; defender: 6H",
+            "trump: 9S; attacker: 6H; defender: 7H; table: 6H/8H", "trump: 9S; attacker: 2H; defender: 7H", "trump: 2S; attacker: 6H; defender: 7H",
+            "trump: 9S; attacker: 6H; defender: 7H; table: 8D/6D", "trump: 9S; attacker: 6H; defender: 7H; table: 8D/AC",
+            "trump: 9S; attacker: 6H; defender: ; table: 8D", "trump: 9S; attacker: ; defender: 7H; table: 8D/9D", "trump: 9S; defender: 7H"]
+        {
+            assert!(matches!(parse_position(position, & settings), Err(Error::InvalidArgument(_))), "{position}");
+        }
+        assert!(matches!(parse_position("trump: 9S; attacker: 6X; defender: 7H", & settings), Err(Error::InvalidCard(_))));
+
+        // returning to the earlier position is a draw, but only on that line: stored outcomes stay right for other lines
+        let state = parse_position("trump: 9S; attacker: 6H 7C; defender: 8H AS", & settings).unwrap();
+        let mut next_state = state.clone();
+        assert!(next_state.apply(Action::Attack(Card::new(Value::Six, Suit::Heart))).is_ok());
+        let mut solver = Solver::new();
+        assert_eq!(solver.solve_after(& state, 0, & [next_state]).unwrap().outcome, Outcome::Draw);
+        assert_eq!(solver.solve(& state, 0).unwrap().outcome, Outcome::Loss);
+
+        // the same cards with another trump are another position
+        let state = parse_position("trump: 9C; attacker: 6H 7C; defender: 8H AS", & settings).unwrap();
+        assert_eq!(solver.solve(& state, 0).unwrap().outcome, Outcome::Win);
+
+        let mut state = GameState::new(& settings);
+        state.deal(2, None, &mut GameRng::seed_from_u64(1));
+        assert!(matches!(Solver::new().solve(& state, 0), Err(Error::NotEndgame)));
+
+        // random endgames: outcomes for both players agree and the best action is legal
+        for seed in 0..10
+        {
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut state = GameState::new(& settings);
+            state.deal(2, None, &mut rng);
+            while !state.is_game_over() && (state.table().remain_cards_count() > 0 || state.hand(0).len() + state.hand(1).len() > 10)
+            {
+                let action = *state.legal_actions().choose(&mut rng).unwrap();
+                state.apply(action).unwrap();
+            }
+
+            let solution = Solver::new().solve(& state, 0).unwrap();
+            let opponent_solution = Solver::new().solve(& state, 1).unwrap();
+            assert_eq!(solution.outcome, match opponent_solution.outcome
+            {
+                Outcome::Win => Outcome::Loss,
+                Outcome::Draw => Outcome::Draw,
+                Outcome::Loss => Outcome::Win,
+            });
+            if let Some(action) = solution.best_action
+            {
+                assert!(state.legal_actions().contains(& action));
+            }
+        }
+
+        let state = parse_position("trump: 9S; attacker: 6H 7H 8C 9D JS QH; defender: 6C 7D 10H KC AS AD", & settings).unwrap();
+        assert!(matches!(Solver::new().max_nodes_count(10).solve(& state, 0), Err(Error::SolverLimitExceeded(10))));
+    }
+
+    #[test]
+    fn hints()
+    {
+        let settings = SettingsBuilder::new().build();
+        let mut advisor = Advisor::new().bot(quick_bot(BotDificulty::Hard)).seed(0);
+        let mut advise = |position: & str|
+        {
+            let state = parse_position(position, & settings).unwrap();
+            let player_index = state.current_player().unwrap();
+            match state.phase()
+            {
+                Phase::Defense => advisor.advise_defense(state.hand(player_index), state.table()),
+                phase => advisor.advise_attack(state.hand(player_index), state.table(), phase == Phase::Attack, false, state.hand(1).len()),
+            }
+        };
+
+        let hint = advise("trump: 9S; attacker: 7H KC KS; defender: 10D JD; table: 7D/8D");
+        assert_eq!(hint.action, Action::ThrowIn(Card::new(Value::Seven, Suit::Heart)));
+        assert_eq!(hint.rationale, "throw-in matches rank 7 on table, saves trump");
+
+        let hint = advise("trump: 9S; attacker: 6C; defender: 8H AS; table: 7H");
+        assert_eq!(hint.action, Action::Defend(0, Card::new(Value::Eight, Suit::Heart)));
+        assert_eq!(hint.to_string(), format!("{}: cheapest card beating the 7♥, saves trump", hint.action));
+
+        let hint = advise("trump: 9S; attacker: 6C; defender: 8H 6D; table: 10H");
+        assert_eq!(hint.action, Action::Take);
+        assert_eq!(hint.rationale, "nothing beats the attack");
+
+        // a trump defense is explained by whether a plain card could beat the attack as well
+        let state = parse_position("trump: 9S; attacker: 6C; defender: 8H AS; table: 7H", & settings).unwrap();
+        let trump_defense = Action::Defend(0, Card::new(Value::Ace, Suit::Spade));
+        assert_eq!(Hint::new(trump_defense, state.hand(1), state.table()).rationale, "trump chosen over the 8♥");
+        let state = parse_position("trump: 9S; attacker: 6C; defender: 6H AS; table: 7H", & settings).unwrap();
+        assert_eq!(Hint::new(trump_defense, state.hand(1), state.table()).rationale, "only a trump beats the 7♥");
+
+        let settings = SettingsBuilder::new().variant(Variant::Perevodnoy).build();
+        let state = parse_position("trump: 9S; attacker: 7C 8D; defender: 6H KD AS; table: 6D", & settings).unwrap();
+        let hint = Advisor::new().bot(quick_bot(BotDificulty::Hard)).seed(0).advise_transfer(state.hand(1), state.table(), state.hand(0).len(), false);
+        assert_eq!(hint.action, Action::Transfer(Card::new(Value::Six, Suit::Heart)));
+        assert_eq!(hint.rationale, "passes the attack on with another 6, saves trump");
+
+        // hints are enabled for one player or for every human player by settings
+        for (hints, mut player, is_hinted) in [(false, RealPlayer::new("Ann"), false), (false, RealPlayer::new("Bob").advisor(Advisor::new()), true), (true, RealPlayer::new("Eve"), true)]
+        {
+            player.notice_game_start(0, 2, & SettingsBuilder::new().hints(hints).build());
+            assert_eq!(player.has_advisor(), is_hinted);
+        }
+    }
+
+    #[test]
+    fn seeded_game_is_reproducible()
+    {
+        let (events, result) = play_seeded_game(42);
+        let (same_events, same_result) = play_seeded_game(42);
+        assert!(events.iter().any(|event| matches!(event, Event::CardsTaken {..})));
+        assert_eq!(events, same_events);
+        assert_eq!(result.finishing_order, same_result.finishing_order);
+        assert_eq!(result.durak, same_result.durak);
+        assert_eq!(result.rounds_count, same_result.rounds_count);
+        for (stats, same_stats) in result.players_stats.iter().zip(same_result.players_stats.iter())
+        {
+            assert_eq!(stats.attack_cards_count, same_stats.attack_cards_count);
+            assert_eq!(stats.beaten_cards_count, same_stats.beaten_cards_count);
+            assert_eq!(stats.taken_cards_count, same_stats.taken_cards_count);
+        }
+
+        let (other_events, _) = play_seeded_game(43);
+        assert_ne!(events, other_events);
+    }
+
+    #[test]
+    fn card_conversions()
+    {
+        assert_eq!(Value::try_from(2).unwrap(), Value::Two);
+        assert_eq!(Value::try_from(15).unwrap(), Value::Joker);
+        assert!(matches!(Value::try_from(16), Err(Error::InvalidCardValue(16))));
+        assert_eq!(Suit::try_from(1).unwrap(), Suit::Spade);
+        assert!(matches!(Suit::try_from(4), Err(Error::InvalidCardSuit(4))));
+
+        let mut table = Table::new(& SettingsBuilder::new().build());
+        assert!(matches!(table.draw_played_cards(), Err(Error::NoPlayedCards)));
+
+        assert_eq!("10H".parse::<Card>().unwrap(), Card::new(Value::Ten, Suit::Heart));
+        assert_eq!("qc".parse::<Card>().unwrap(), Card::new(Value::Queen, Suit::Club));
+        assert_eq!("A♦".parse::<Card>().unwrap(), Card::new(Value::Ace, Suit::Diamond));
+        assert!("RJ".parse::<Card>().unwrap().is_joker());
+        assert!(matches!("1S".parse::<Card>(), Err(Error::InvalidCard(_))));
+        assert!(matches!("KX".parse::<Card>(), Err(Error::InvalidCard(_))));
+    }
+
+    /// Always plays the last card of the hand, whatever is on the table
+    struct BrokenPlayer
+    {
+        cards              : Vec<Card>,
+        illegal_moves_count: Rc<Cell<usize>>,
+    }
+
+    impl Player for BrokenPlayer
+    {
+        fn cards(& self) -> & Vec<Card> { & self.cards }
+        fn cards_mut(&mut self) -> &mut Vec<Card> { &mut self.cards }
+        fn name(& self) -> & str { "Broken" }
+        fn notice_trump_card(&mut self, _trump_card: Card, _holder_name: Option<& str>) {}
+
+        fn notice_illegal_move(&mut self, _error: & Error)
+        {
+            self.illegal_moves_count.set(self.illegal_moves_count.get() + 1);
+        }
+
+        fn play_attack_card(&mut self, _table: & Table, _is_first_attack: bool, _defender_cards_count: usize, _rng: &mut GameRng) -> Option<Card>
+        {
+            self.cards.pop()
+        }
+
+        fn play_card_to_take(&mut self, _table: & Table, _defender_cards_count: usize, _rng: &mut GameRng) -> Option<Card>
+        {
+            self.cards.pop()
+        }
+
+        fn play_defense_card(&mut self, table: & Table, _rng: &mut GameRng) -> Option<(usize, Card)>
+        {
+            self.cards.pop().map(|card| (table.attack_cards().len() + 1, card))
+        }
+
+        fn play_transfer_card(&mut self, _table: & Table, _next_defender_cards_count: usize, _can_show_trump: bool, _rng: &mut GameRng) -> Option<Transfer>
+        {
+            self.cards.pop().map(Transfer::Card)
+        }
+
+        fn challenge_cheat(&mut self, table: & Table, _rng: &mut GameRng) -> Option<Card>
+        {
+            table.attack_cards().first().copied()
+        }
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected()
+    {
+        for variant in [Variant::Podkidnoy, Variant::Perevodnoy]
+        {
+            let illegal_moves_count = Rc::new(Cell::new(0));
+            let mut game = Game::new(SettingsBuilder::new().variant(variant).max_illegal_moves(2).seed(7).build());
+            assert!(game.add_player(Box::new(BrokenPlayer {cards: vec![], illegal_moves_count: illegal_moves_count.clone()})).is_ok());
+            assert!(game.add_player(quick_bot(BotDificulty::Easy)).is_ok());
+
+            let result = game.start().unwrap();
+            assert!(illegal_moves_count.get() > 0);
+            assert!(result.rounds_count > 0);
+        }
+    }
+
+    #[test]
+    fn suspicious_cards()
+    {
+        let mut table = Table::new(& SettingsBuilder::new().cheats_allowed(true).build());
+        table.reset(&mut GameRng::seed_from_u64(0));
+        assert!(table.cheats_allowed());
+        let trump = table.trump();
+        let suits: Vec<Suit> = [Suit::Club, Suit::Spade, Suit::Heart, Suit::Diamond].into_iter().filter(|suit| *suit != trump).collect();
+
+        table.take_attack_card(Card::new(Value::Ten, suits[0]));
+        table.take_defense_card(Card::new(Value::Six, suits[0]), 0);
+        table.take_attack_card(Card::new(Value::Six, suits[1]));
+        table.take_attack_card(Card::new(Value::Eight, suits[1]));
+        assert_eq!(table.suspicious_cards(), vec![Card::new(Value::Eight, suits[1]), Card::new(Value::Six, suits[0])]);
+
+        // jokers are thrown in by value as well
+        table.take_attack_card(Card::joker(true));
+        assert_eq!(table.suspicious_cards(), vec![Card::new(Value::Eight, suits[1]), Card::joker(true), Card::new(Value::Six, suits[0])]);
+    }
+
+    #[test]
+    fn cheating_game()
+    {
+        let mut caught_cheats_count = 0;
+        let mut false_accusations_count = 0;
+        for seed in 0..20
+        {
+            let mut game = Game::new(SettingsBuilder::new().cheats_allowed(true).finish_after_first_win(false).seed(seed).build());
+            for difficulty in [BotDificulty::Easy, BotDificulty::Medium, BotDificulty::Hard]
+            {
+                assert!(game.add_player(quick_bot(difficulty)).is_ok());
+            }
+
+            let result = game.start().unwrap();
+            caught_cheats_count += result.players_stats.iter().map(|stats| stats.caught_cheats_count).sum::<usize>();
+            false_accusations_count += result.players_stats.iter().map(|stats| stats.false_accusations_count).sum::<usize>();
+        }
+        assert!(caught_cheats_count > 0);
+        assert!(false_accusations_count > 0);
+    }
+
+    fn assert_cards_conserved(state: & GameState)
+    {
+        let table = state.table();
+        let cards_count = (0..state.players_count()).map(|player_index| state.hand(player_index).len()).sum::<usize>()
+            + table.attack_cards().len() + table.defense_cards().len()
+            + table.remain_cards_count() + table.discarded_cards_count();
+        assert_eq!(cards_count, table.card_deck() as usize);
+    }
+
+    #[test]
+    fn random_legal_playout()
+    {
+        for (seed, variant) in (0..10).zip([Variant::Podkidnoy, Variant::Perevodnoy].into_iter().cycle())
+        {
+            let settings = SettingsBuilder::new().variant(variant).trump_show_transfer(true).cheats_allowed(seed % 3 == 0)
+                .defense_after_throw_ins(seed % 4 < 2).card_deck(Deck::Extended).finish_after_first_win(false).build();
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut state = GameState::new(& settings);
+            assert!(state.legal_actions().is_empty());
+            assert!(matches!(state.apply(Action::Pass), Err(Error::UnexpectedAction)));
+
+            state.deal(4, None, &mut rng);
+            assert_eq!(state.phase(), Phase::Attack);
+            assert!(matches!(state.apply(Action::Pass), Err(Error::UnexpectedAction)));
+            let absent_card = (0..state.players_count()).flat_map(|player_index| state.hand(player_index).clone())
+                .find(|card| !state.hand(state.current_player().unwrap()).contains(card))
+                .unwrap();
+            assert!(matches!(state.apply(Action::Attack(absent_card)), Err(Error::AbsentCard(_))));
+
+            while !state.is_game_over()
+            {
+                assert_cards_conserved(& state);
+                let action = *state.legal_actions().choose(&mut rng).unwrap();
+                assert!(state.apply(action).is_ok(), "{:?} is legal", action);
+            }
+            assert_cards_conserved(& state);
+            assert!(state.current_player().is_none());
+            assert!(state.active_players().len() <= 1 || state.is_stalemate());
+        }
+    }
+
+    fn assert_knowledge_consistent(knowledge: & Knowledge, player_index: usize, state: & GameState)
+    {
+        let mut own_cards = knowledge.known_cards(player_index).clone();
+        own_cards.sort();
+        assert_eq!(& own_cards, state.hand(player_index));
+        assert_eq!(knowledge.discarded_cards().len(), state.table().discarded_cards_count());
+
+        // every card which isn't known is either in the stock under the trump card or in opponent's hand
+        let mut hidden_cards_count = state.table().remain_cards_count().saturating_sub(1);
+        for opponent_index in (0..state.players_count()).filter(|opponent_index| *opponent_index != player_index)
+        {
+            let hand = state.hand(opponent_index);
+            assert_eq!(knowledge.hand_size(opponent_index), hand.len());
+            assert!(knowledge.known_cards(opponent_index).iter().all(|card| hand.contains(card)));
+            hidden_cards_count += hand.len() - knowledge.known_cards(opponent_index).len();
+        }
+        assert_eq!(knowledge.unknown_cards().len(), hidden_cards_count);
+        assert!(knowledge.unknown_cards().iter().all(|card| !state.hand(player_index).contains(card)));
+    }
+
+    #[test]
+    fn card_counting()
+    {
+        for (seed, variant) in (0..10).zip([Variant::Podkidnoy, Variant::Perevodnoy].into_iter().cycle())
+        {
+            let settings = SettingsBuilder::new().variant(variant).trump_show_transfer(true).cheats_allowed(seed % 3 == 0)
+                .card_deck(Deck::Extended).finish_after_first_win(false).build();
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut state = GameState::new(& settings);
+            let mut knowledges: Vec<Knowledge> = (0..3).map(|player_index| Knowledge::new(player_index, 3, Deck::Extended)).collect();
+
+            let mut events = state.deal(3, None, &mut rng);
+            let mut is_opponent_card_known = false;
+            for knowledge in knowledges.iter_mut()
+            {
+                knowledge.notice_trump_card(state.table().trump_card());
+            }
+            loop
+            {
+                for (player_index, knowledge) in knowledges.iter_mut().enumerate()
+                {
+                    events.iter().for_each(|event| knowledge.notice_event(event));
+                    assert_knowledge_consistent(knowledge, player_index, & state);
+                    if !state.is_game_over()
+                    {
+                        assert_eq!(knowledge.defending_index(), Some(state.defending_index()));
+                    }
+                }
+                is_opponent_card_known |= knowledges[0].opponents_cards().iter().any(|(_, is_known)| *is_known);
+                if state.is_game_over()
+                {
+                    break;
+                }
+                let action = *state.legal_actions().choose(&mut rng).unwrap();
+                events = state.apply(action).unwrap();
+            }
+
+            // cards taken from the table are known to everybody
+            assert!(is_opponent_card_known);
+        }
+    }
+
+    #[test]
+    fn defense_after_throw_ins()
+    {
+        let settings = SettingsBuilder::new().defense_after_throw_ins(true).build();
+        let mut state = GameState::new(& settings);
+        state.deal(3, None, &mut GameRng::seed_from_u64(3));
+        let attacking_index = state.attacking_index();
+        let defending_index = state.defending_index();
+        let card = state.hand(attacking_index)[0];
+        assert!(state.apply(Action::Attack(card)).is_ok());
+
+        // defender waits until all attackers pass
+        assert_eq!(state.phase(), Phase::ThrowIn);
+        assert_eq!(state.current_player(), Some(attacking_index));
+        while state.phase() == Phase::ThrowIn
+        {
+            let action = state.legal_actions()[0];
+            assert!(state.apply(action).is_ok());
+        }
+        assert_eq!(state.phase(), Phase::Defense);
+        assert_eq!(state.current_player(), Some(defending_index));
+        assert!(state.table().unbeaten_cards_count() > 0);
+
+        // the whole assignment is rejected when one of the defenses is illegal
+        let table = state.table().clone();
+        let unbeaten_indices: Vec<usize> = (0..table.played_cards().len()).filter(|index| table.played_cards()[*index].1.is_none()).collect();
+        let hand = state.hand(defending_index).clone();
+        let legal_defenses = table.legal_defenses(& hand);
+        let illegal_card = *hand.iter().find(|card| table.check_defense_card(card, unbeaten_indices[0]).is_err()).unwrap();
+        let mut defenses: Vec<Action> = legal_defenses.iter().filter(|(index, _)| *index != unbeaten_indices[0]).take(1)
+            .map(|(index, card)| Action::Defend(*index, *card))
+            .collect();
+        defenses.push(Action::Defend(unbeaten_indices[0], illegal_card));
+        assert!(matches!(state.apply_all(& defenses), Err(Error::IncorrectDefense)));
+        assert_eq!(state.table().unbeaten_cards_count(), table.unbeaten_cards_count());
+        assert_eq!(state.hand(defending_index), & hand);
+        assert!(state.apply(Action::Take).is_ok());
+        while state.is_defender_taking()
+        {
+            assert!(state.apply(Action::Pass).is_ok());
+        }
+        assert_eq!(state.hand(defending_index).len(), hand.len() + table.played_cards().len());
+
+        for seed in 0..5
+        {
+            let mut game = Game::new(SettingsBuilder::new().defense_after_throw_ins(true).variant(Variant::Perevodnoy).seed(seed).build());
+            for difficulty in [BotDificulty::Easy, BotDificulty::Medium, BotDificulty::Hard]
+            {
+                assert!(game.add_player(quick_bot(difficulty)).is_ok());
+            }
+            let result = game.start().unwrap();
+            assert!(result.players_stats.iter().map(|stats| stats.beaten_cards_count).sum::<usize>() > 0);
+        }
+    }
+
+    #[test]
+    fn throw_ins_to_taking_defender()
+    {
+        let settings = SettingsBuilder::new().build();
+        let mut state = GameState::new(& settings);
+        let mut rng = GameRng::seed_from_u64(0);
+        let (card, pair_card) = loop
+        {
+            state.deal(2, None, &mut rng);
+            let hand = state.hand(state.attacking_index());
+            let pair = hand.iter()
+                .find_map(|card| hand.iter().find(|other| *other != card && other.value() == card.value()).map(|other| (*card, *other)));
+            if let Some(pair) = pair
+            {
+                break pair;
+            }
+        };
+        let attacking_index = state.attacking_index();
+        let defending_index = state.defending_index();
+        let defender_cards_count = state.hand(defending_index).len();
+
+        assert!(state.apply(Action::Attack(card)).is_ok());
+        assert!(state.apply(Action::Take).is_ok());
+        assert!(state.is_defender_taking());
+        assert_eq!(state.current_player(), Some(attacking_index));
+        assert!(state.legal_actions().contains(& Action::ThrowIn(pair_card)));
+        assert!(state.legal_actions().contains(& Action::Pass));
+
+        assert!(state.apply(Action::ThrowIn(pair_card)).is_ok());
+        while state.is_defender_taking()
+        {
+            assert!(state.apply(Action::Pass).is_ok());
+        }
+        assert!(state.hand(defending_index).contains(& card));
+        assert!(state.hand(defending_index).contains(& pair_card));
+        assert_eq!(state.hand(defending_index).len(), defender_cards_count + 2);
+        assert_eq!(state.first_attacking_index(), attacking_index);
+    }
+
+    #[test]
+    fn throw_in_rules()
+    {
+        for throw_in_rule in [ThrowInRule::Everybody, ThrowInRule::Neighbors, ThrowInRule::LeftNeighbor]
+        {
+            let settings = SettingsBuilder::new().throw_in_rule(throw_in_rule).finish_after_first_win(false).build();
+            let mut rng = GameRng::seed_from_u64(5);
+            let mut state = GameState::new(& settings);
+            state.deal(4, None, &mut rng);
+
+            let defending_index = state.defending_index();
+            let left_index = state.previous_active_index(defending_index);
+            let right_index = state.next_active_index(defending_index);
+            let opposite_index = state.next_active_index(right_index);
+            assert_eq!(left_index, state.attacking_index());
+            assert!(!state.can_throw_in(defending_index));
+            assert!(state.can_throw_in(left_index));
+            assert_eq!(state.can_throw_in(right_index), throw_in_rule != ThrowInRule::LeftNeighbor);
+            assert_eq!(state.can_throw_in(opposite_index), throw_in_rule == ThrowInRule::Everybody);
+
+            while !state.is_game_over()
+            {
+                if state.phase() == Phase::ThrowIn
+                {
+                    let attacking_index = state.current_player().unwrap();
+                    let defending_index = state.defending_index();
+                    assert!(state.can_throw_in(attacking_index));
+                    match throw_in_rule
+                    {
+                        ThrowInRule::Everybody => (),
+                        ThrowInRule::Neighbors => assert!(attacking_index == state.previous_active_index(defending_index)
+                            || attacking_index == state.next_active_index(defending_index)),
+                        ThrowInRule::LeftNeighbor => assert_eq!(attacking_index, state.previous_active_index(defending_index)),
+                    }
+                }
+                let action = *state.legal_actions().choose(&mut rng).unwrap();
+                assert!(state.apply(action).is_ok());
+            }
+        }
+
+        // after a transfer the player who transferred throws in, not the one who started the attack
+        let settings = SettingsBuilder::new().variant(Variant::Perevodnoy).throw_in_rule(ThrowInRule::LeftNeighbor).finish_after_first_win(false).build();
+        let mut is_transfer_checked = false;
+        for seed in 0..20
+        {
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut state = GameState::new(& settings);
+            state.deal(4, None, &mut rng);
+            let mut bout_attacking_index = state.attacking_index();
+            while !state.is_game_over() && !is_transfer_checked
+            {
+                if state.phase() == Phase::Attack
+                {
+                    bout_attacking_index = state.current_player().unwrap();
+                }
+
+                let actions = state.legal_actions();
+                let transfer = actions.iter().find(|action| matches!(action, Action::Transfer(_))).filter(|_| state.active_players().len() > 2);
+                let transfer = match transfer
+                {
+                    Some(transfer) => transfer,
+                    None =>
+                    {
+                        assert!(state.apply(*actions.choose(&mut rng).unwrap()).is_ok());
+                        continue;
+                    },
+                };
+
+                let transferring_index = state.defending_index();
+                assert!(state.apply(*transfer).is_ok());
+                assert_eq!(state.previous_active_index(state.defending_index()), transferring_index);
+                assert_eq!(state.can_throw_in(transferring_index), !state.hand(transferring_index).is_empty());
+                assert!(!state.can_throw_in(bout_attacking_index));
+                is_transfer_checked = true;
+            }
+        }
+        assert!(is_transfer_checked);
+    }
+
+    #[test]
+    fn team_play()
+    {
+        let mut game = Game::new(SettingsBuilder::new().team_play(true).build());
+        for difficulty in [BotDificulty::Easy, BotDificulty::Medium, BotDificulty::Hard]
+        {
+            assert!(game.add_player(quick_bot(difficulty)).is_ok());
+        }
+        assert!(matches!(game.start(), Err(Error::UnevenTeams(3))));
+
+        // neighbors are the defender's opponents: the attacker skipped a finished player and his/her partner to attack
+        let card = |value, suit| Card::new(value, suit);
+        for throw_in_rule in [ThrowInRule::Neighbors, ThrowInRule::LeftNeighbor]
+        {
+            let settings = SettingsBuilder::new().team_play(true).throw_in_rule(throw_in_rule).finish_after_first_win(false).build();
+            let mut table = Table::new(& settings);
+            table.set_endgame(card(Value::Nine, Suit::Spade), vec![(card(Value::Seven, Suit::Diamond), None)]);
+            let hands = vec![vec![card(Value::Eight, Suit::Heart)], vec![card(Value::Seven, Suit::Club)], vec![], vec![card(Value::Seven, Suit::Heart)]];
+            let round = RoundPosition {first_attacking_index: 1, attacking_index: 1, defending_index: 0, ..Default::default()};
+            let state = GameState::from_position(& settings, table, hands, vec![2], Phase::ThrowIn, round);
+            assert_eq!(state.next_opponent_index(1), 0);
+            assert!(state.can_throw_in(1));
+            assert!(state.can_throw_in(3));
+            assert!(!state.can_throw_in(0));
+        }
+
+        for (seed, players_count) in (0..6).zip([4, 6].into_iter().cycle())
+        {
+            // partners never attack each other
+            let settings = SettingsBuilder::new().team_play(true).variant(Variant::Perevodnoy).seed(seed).build();
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut state = GameState::new(& settings);
+            state.deal(players_count, None, &mut rng);
+            while !state.is_game_over()
+            {
+                if matches!(state.phase(), Phase::Attack | Phase::ThrowIn)
+                {
+                    assert_ne!(state.team(state.current_player().unwrap()), state.team(state.defending_index()));
+                }
+                let action = *state.legal_actions().choose(&mut rng).unwrap();
+                assert!(state.apply(action).is_ok());
+            }
+
+            let mut game = Game::new(settings);
+            for player_index in 0..players_count
+            {
+                assert!(game.add_player(quick_bot(if player_index % 3 == 0 {BotDificulty::Hard} else {BotDificulty::Medium})).is_ok());
+            }
+            let result = game.start().unwrap();
+            assert!(result.players_stats.iter().enumerate().all(|(player_index, stats)| stats.team == Some(player_index % 2)));
+            if let Some(losing_team) = result.losing_team
+            {
+                assert!(!result.is_draw);
+                assert!(game.active_players().iter().all(|player_index| *player_index % 2 == losing_team));
+                assert!(result.durak.map_or(true, |durak| durak % 2 == losing_team));
+                assert!(result.to_string().contains(& format!("Team {} (", losing_team + 1)));
+            }
+            else
+            {
+                assert!(result.is_draw);
+            }
+        }
+    }
+
+    #[test]
+    fn match_scores()
+    {
+        let mut game = Game::new(SettingsBuilder::new().seed(11).build());
+        for difficulty in [BotDificulty::Easy, BotDificulty::Medium, BotDificulty::Hard]
+        {
+            assert!(game.add_player(quick_bot(difficulty)).is_ok());
+        }
+
+        let mut game_match = Match::new(game, 6);
+        let scores = game_match.start().unwrap();
+        let results = game_match.results();
+        assert_eq!(results.len(), 6);
+        assert!(game_match.is_over());
+        assert_eq!(scores.iter().map(|score| score.wins_count).sum::<usize>(), results.iter().filter(|result| !result.finishing_order.is_empty() && !result.is_draw).count());
+        assert_eq!(scores.iter().map(|score| score.duraks_count).sum::<usize>(), results.iter().filter(|result| result.durak.is_some()).count());
+        assert!(scores.iter().all(|score| score.pogony_count <= score.duraks_count));
+
+        // the player after the previous durak starts
+        for (previous, result) in results.iter().zip(results.iter().skip(1))
+        {
+            if let Some(durak_index) = previous.durak
+            {
+                assert_eq!(result.starting_index, (durak_index + 1) % scores.len());
+            }
+        }
+
+        // three players playing until the first win leave nobody alone with cards
+        let mut game = Game::new(SettingsBuilder::new().seed(12).build());
+        for difficulty in [BotDificulty::Easy, BotDificulty::Medium, BotDificulty::Hard]
+        {
+            assert!(game.add_player(quick_bot(difficulty)).is_ok());
+        }
+        let mut game_match = Match::new(game, 4);
+        let scores = game_match.start().unwrap();
+        assert!(game_match.results().iter().all(|result| result.durak.is_none() && result.finishing_order.len() <= 1));
+        assert!(scores.iter().all(|score| score.duraks_count == 0));
+        assert_eq!(scores.iter().map(|score| score.wins_count).sum::<usize>(), game_match.results().iter().filter(|result| !result.is_draw).count());
+
+        // players who got rid of the cards before the last ones did it simultaneously don't win a draw
+        let mut game = Game::new(SettingsBuilder::new().seed(98).finish_after_first_win(false).build());
+        for _ in 0..3
+        {
+            assert!(game.add_player(quick_bot(BotDificulty::Easy)).is_ok());
+        }
+        let mut game_match = Match::new(game, 1);
+        let scores = game_match.start().unwrap();
+        assert!(game_match.results()[0].is_draw && !game_match.results()[0].finishing_order.is_empty());
+        assert!(scores.iter().all(|score| score.wins_count == 0));
+
+        let mut game = Game::new(SettingsBuilder::new().seed(11).build());
+        for difficulty in [BotDificulty::Easy, BotDificulty::Hard]
+        {
+            assert!(game.add_player(quick_bot(difficulty)).is_ok());
+        }
+        let mut game_match = Match::new(game, 100).duraks_limit(2);
+        let scores = game_match.start().unwrap();
+        assert!(game_match.results().len() < 100);
+        assert_eq!(scores.iter().map(|score| score.duraks_count).max(), Some(2));
+        assert!(game_match.to_string().contains("Match score after"));
+    }
+
+    fn parse_args(args: & [& str]) -> Result<Config, Error>
+    {
+        Config::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn command_line_arguments()
+    {
+        let config = parse_args(& []).unwrap();
+        assert_eq!(config.bots, vec![BotDificulty::Easy, BotDificulty::Medium, BotDificulty::Hard]);
+        assert!(config.players_names.is_empty());
+        assert!(!config.show_help);
+
+        let config = parse_args(& ["--deck", "54", "--variant", "perevodnoy", "--bots", "hard,easy", "--player", "Ann", "--player", "Bob",
+            "--seed", "42", "--until-one-remains", "--games", "3", "--solve", "trump: 9S; attacker: AS; defender: 6H"]).unwrap();
+        assert_eq!(config.bots, vec![BotDificulty::Hard, BotDificulty::Easy]);
+        assert_eq!(config.players_names, vec!["Ann".to_string(), "Bob".to_string()]);
+        assert_eq!(config.games_count, 3);
+        assert_eq!(config.position.as_deref(), Some("trump: 9S; attacker: AS; defender: 6H"));
+        let settings = config.settings.build();
+        assert_eq!(settings.card_deck(), Deck::Extended);
+        assert_eq!(settings.variant(), Variant::Perevodnoy);
+        assert!(!settings.finish_after_first_win());
+        assert_eq!(Game::new(settings).seed(), 42);
+
+        assert_eq!(parse_args(& ["--bots", "4"]).unwrap().bots, vec![BotDificulty::Medium; 4]);
+        assert!(parse_args(& ["-h"]).unwrap().show_help);
+        assert!(matches!(parse_args(& ["--deck", "40"]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(parse_args(& ["--bots", "easy,smart"]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(parse_args(& ["--games", "0"]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(parse_args(& ["--seed"]), Err(Error::MissingArgumentValue(_))));
+        assert!(matches!(parse_args(& ["--colour"]), Err(Error::InvalidArgument(_))));
+        assert_eq!(parse_args(& ["--player", "Ann", "--hints", "Ann"]).unwrap().hinted_names, vec!["Ann".to_string()]);
+        assert!(matches!(parse_args(& ["--hints", "Bob"]), Err(Error::InvalidArgument(_))));
+        assert_eq!(parse_args(& []).unwrap().expert_budget, MctsBot::DEFAULT_BUDGET);
+        assert_eq!(parse_args(& ["--expert-budget", "300ms"]).unwrap().expert_budget, SearchBudget::Time(Duration::from_millis(300)));
+        assert_eq!(parse_args(& ["--expert-budget", "2s"]).unwrap().expert_budget, SearchBudget::Time(Duration::from_secs(2)));
+        assert_eq!(parse_args(& ["--expert-budget", "200"]).unwrap().expert_budget, SearchBudget::Iterations(200));
+        assert!(matches!(parse_args(& ["--expert-budget", "0"]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(parse_args(& ["--expert-budget", "fast"]), Err(Error::InvalidArgument(_))));
+    }
 }
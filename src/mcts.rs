@@ -0,0 +1,377 @@
+use std::time::{Duration, Instant};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+
+use crate::cards;
+use crate::game::{Settings, SettingsBuilder};
+use crate::knowledge::Knowledge;
+use crate::player::{next_bot_number, BotDificulty, Player};
+use crate::state::{Action, Event, GameState, Phase};
+use crate::table;
+use crate::utils::*;
+
+/// How long the bot thinks over every decision
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchBudget
+{
+    Iterations(usize),
+    Time(Duration),
+}
+
+impl std::str::FromStr for SearchBudget
+{
+    type Err = Error;
+
+    /// Thinking time like "500ms" or "2s", a plain number is the number of iterations
+    fn from_str(text: & str) -> Result<Self, Self::Err>
+    {
+        let invalid_budget = || Error::InvalidArgument(text.to_string());
+        let parse_count = |count: & str| count.parse::<u64>().ok().filter(|count| *count > 0).ok_or_else(invalid_budget);
+        if let Some(milliseconds) = text.strip_suffix("ms")
+        {
+            Ok(Self::Time(Duration::from_millis(parse_count(milliseconds)?)))
+        }
+        else if let Some(seconds) = text.strip_suffix('s')
+        {
+            Ok(Self::Time(Duration::from_secs(parse_count(seconds)?)))
+        }
+        else
+        {
+            Ok(Self::Iterations(parse_count(text)? as usize))
+        }
+    }
+}
+
+struct Node
+{
+    action             : Option<Action>,
+    /// Player who made the action
+    player_index       : usize,
+    children           : Vec<usize>,
+    visits_count       : usize,
+    /// Number of iterations where the action was legal
+    availability_count : usize,
+    total_reward       : f64,
+}
+
+impl Node
+{
+    fn new(action: Option<Action>, player_index: usize) -> Self
+    {
+        Self {action, player_index, children: vec![], visits_count: 0, availability_count: 1, total_reward: 0.0}
+    }
+}
+
+/// Information set Monte Carlo tree search: every iteration guesses opponents' hands and the stock consistently with
+/// the known cards, descends the tree of actions which are legal in the guess and finishes the game with fast rollouts
+pub struct MctsBot
+{
+    cards        : Vec<cards::Card>,
+    name         : String,
+    budget       : SearchBudget,
+    settings     : Settings,
+    knowledge    : Knowledge,
+}
+
+impl MctsBot
+{
+    const EXPLORATION: f64 = 0.7;
+    /// Rollouts of games which got stuck are cut and judged by the number of cards
+    const MAX_ROLLOUT_ACTIONS_COUNT: usize = 500;
+    pub const DEFAULT_BUDGET: SearchBudget = SearchBudget::Time(Duration::from_secs(1));
+
+    pub fn new() -> Self
+    {
+        Self
+        {
+            cards        : vec![],
+            name         : format!("Bot #{} ({})", next_bot_number(), BotDificulty::Expert),
+            budget       : Self::DEFAULT_BUDGET,
+            settings     : SettingsBuilder::new().build(),
+            knowledge    : Knowledge::default(),
+        }
+    }
+
+    pub fn budget(mut self, budget: SearchBudget) -> Self
+    {
+        self.budget = budget;
+        self
+    }
+
+    /// Guesses hidden cards: opponents get their known cards and random unknown ones, the rest of them lies in the stock
+    fn determinize(& self, table: & table::Table, phase: Phase, rng: &mut GameRng) -> Option<GameState>
+    {
+        let mut unknown_cards = self.knowledge.unknown_cards().clone();
+        unknown_cards.shuffle(rng);
+        self.knowledge.state(& self.settings, & self.cards, table, phase, unknown_cards)
+    }
+
+    fn is_budget_spent(& self, iterations_count: usize, start: Instant) -> bool
+    {
+        match self.budget
+        {
+            SearchBudget::Iterations(max_iterations_count) => iterations_count >= max_iterations_count,
+            // at least one iteration is needed to choose anything
+            SearchBudget::Time(duration) => iterations_count > 0 && start.elapsed() >= duration,
+        }
+    }
+
+    /// Accusations in simulations would be false anyway, since guessed moves are always legal
+    fn tree_actions(state: & GameState) -> Vec<Action>
+    {
+        match state.phase()
+        {
+            Phase::Challenge(_) => vec![Action::Pass],
+            _ => state.legal_actions(),
+        }
+    }
+
+    /// Fast policy of rollouts: the lowest cards are played, trumps are thrown in only when the stock is over
+    fn rollout_action(state: & GameState) -> Option<Action>
+    {
+        let trump = state.table().trump();
+        let is_stock_empty = state.table().remain_cards_count() == 0;
+        let weight = |action: & Action| match action
+        {
+            Action::Attack(card) | Action::ThrowIn(card) | Action::Defend(_, card) => card.weight(trump),
+            _ => usize::MAX,
+        };
+
+        let actions = state.legal_actions().into_iter();
+        match state.phase()
+        {
+            Phase::Attack => actions.min_by_key(weight),
+            Phase::ThrowIn => actions
+                .filter(|action| !matches!(action, Action::ThrowIn(card) if card.is_joker() || (card.suit() == trump && !is_stock_empty)))
+                .min_by_key(weight),
+            Phase::Defense => actions
+                .filter(|action| matches!(action, Action::Defend(..) | Action::Take))
+                .min_by_key(weight),
+            Phase::Challenge(_) => Some(Action::Pass),
+            Phase::GameOver => None,
+        }
+    }
+
+    fn rollout(state: &mut GameState)
+    {
+        for _ in 0 .. Self::MAX_ROLLOUT_ACTIONS_COUNT
+        {
+            match Self::rollout_action(state)
+            {
+                Some(action) if state.apply(action).is_ok() => (),
+                _ => return,
+            }
+        }
+    }
+
+    /// 1 for a won game, 0 for a lost one and 0.5 for a draw
+    fn reward(state: & GameState, player_index: usize) -> f64
+    {
+        if !state.is_game_over()
+        {
+            let max_cards_count = state.active_players().iter().map(|active_index| state.hand(*active_index).len()).max().unwrap_or(0);
+            return if state.hand(player_index).len() < max_cards_count {1.0} else {0.0};
+        }
+
+        match (state.is_draw(), state.has_lost(player_index))
+        {
+            (true, _) => 0.5,
+            (false, true) => 0.0,
+            (false, false) => 1.0,
+        }
+    }
+
+    /// Upper confidence bound of the child, availability count replaces parent visits count
+    fn score(node: & Node) -> f64
+    {
+        if node.visits_count == 0
+        {
+            return f64::INFINITY;
+        }
+        let visits_count = node.visits_count as f64;
+        node.total_reward / visits_count + Self::EXPLORATION * ((node.availability_count as f64).ln() / visits_count).sqrt()
+    }
+
+    /// Runs the search from the position of the decision, root actions can be limited to some kind of decision.
+    /// The game's random numbers are drawn once: the number of iterations in the thinking time mustn't change the rest of a seeded game
+    fn search(& self, table: & table::Table, phase: Phase, is_root_action: impl Fn(& Action) -> bool, rng: &mut GameRng) -> Option<Action>
+    {
+        let rng = &mut GameRng::seed_from_u64(rng.gen());
+        let mut nodes = vec![Node::new(None, self.knowledge.player_index())];
+        let start = Instant::now();
+        let mut iterations_count = 0;
+        while !self.is_budget_spent(iterations_count, start)
+        {
+            iterations_count += 1;
+            let mut state = self.determinize(table, phase, rng)?;
+            let mut path = vec![0];
+            let mut node_index = 0;
+            while let Some(player_index) = state.current_player()
+            {
+                let mut actions = Self::tree_actions(& state);
+                if node_index == 0
+                {
+                    actions.retain(& is_root_action);
+                }
+
+                let children: Vec<usize> = nodes[node_index].children.iter().copied()
+                    .filter(|child_index| nodes[*child_index].action.is_some_and(|action| actions.contains(& action)))
+                    .collect();
+                for child_index in children.iter()
+                {
+                    nodes[*child_index].availability_count += 1;
+                }
+
+                let untried_actions: Vec<Action> = actions.into_iter()
+                    .filter(|action| !nodes[node_index].children.iter().any(|child_index| nodes[*child_index].action == Some(*action)))
+                    .collect();
+                let (child_index, is_expanded) = match untried_actions.choose(rng)
+                {
+                    Some(action) =>
+                    {
+                        let child_index = nodes.len();
+                        nodes.push(Node::new(Some(*action), player_index));
+                        nodes[node_index].children.push(child_index);
+                        (child_index, true)
+                    },
+                    None => match children.into_iter().max_by(|first, second| Self::score(& nodes[*first]).total_cmp(& Self::score(& nodes[*second])))
+                    {
+                        Some(child_index) => (child_index, false),
+                        None => break,
+                    },
+                };
+
+                let action = nodes[child_index].action.expect("only the root has no action");
+                if state.apply(action).is_err()
+                {
+                    break;
+                }
+                path.push(child_index);
+                node_index = child_index;
+                if is_expanded
+                {
+                    break;
+                }
+            }
+
+            Self::rollout(&mut state);
+            for node_index in path
+            {
+                let node = &mut nodes[node_index];
+                node.visits_count += 1;
+                node.total_reward += Self::reward(& state, node.player_index);
+            }
+        }
+
+        nodes[0].children.iter()
+            .max_by_key(|child_index| nodes[**child_index].visits_count)
+            .and_then(|child_index| nodes[*child_index].action)
+    }
+
+    fn remove_card(&mut self, card: cards::Card)
+    {
+        self.cards.retain(|hand_card| *hand_card != card);
+    }
+}
+
+impl Default for MctsBot
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Player for MctsBot
+{
+    fn name(& self) -> & str
+    {
+        & self.name
+    }
+
+    fn cards(& self) -> & Vec<cards::Card>
+    {
+        & self.cards
+    }
+
+    fn cards_mut(&mut self) -> &mut Vec<cards::Card>
+    {
+        &mut self.cards
+    }
+
+    fn notice_trump_card(&mut self, trump_card: cards::Card, _holder_name: Option<& str>)
+    {
+        self.knowledge.notice_trump_card(trump_card);
+    }
+
+    fn notice_game_start(&mut self, player_index: usize, players_count: usize, settings: & Settings)
+    {
+        self.settings = *settings;
+        self.knowledge = Knowledge::new(player_index, players_count, settings.card_deck());
+    }
+
+    fn notice_event(&mut self, event: & Event)
+    {
+        self.knowledge.notice_event(event);
+    }
+
+    fn play_attack_card(&mut self, table: & table::Table, is_first_attack: bool, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        let phase = if is_first_attack {Phase::Attack} else {Phase::ThrowIn};
+        let card = match self.search(table, phase, |_| true, rng)
+        {
+            Some(Action::Attack(card) | Action::ThrowIn(card)) => card,
+            // the bot hasn't followed the game from the start
+            None if is_first_attack => *self.cards.first()?,
+            _ => return None,
+        };
+        self.remove_card(card);
+        Some(card)
+    }
+
+    fn play_card_to_take(&mut self, table: & table::Table, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        match self.search(table, Phase::ThrowIn, |_| true, rng)?
+        {
+            Action::ThrowIn(card) =>
+            {
+                self.remove_card(card);
+                Some(card)
+            },
+            _ => None,
+        }
+    }
+
+    fn play_defense_card(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<(usize, cards::Card)>
+    {
+        let is_root_action = |action: & Action| matches!(action, Action::Defend(..) | Action::Take);
+        match self.search(table, Phase::Defense, is_root_action, rng)?
+        {
+            Action::Defend(attack_card_index, card) =>
+            {
+                self.remove_card(card);
+                Some((attack_card_index, card))
+            },
+            _ => None,
+        }
+    }
+
+    fn play_transfer_card(&mut self, table: & table::Table, next_defender_cards_count: usize, can_show_trump: bool, rng: &mut GameRng) -> Option<table::Transfer>
+    {
+        match self.search(table, Phase::Defense, |_| true, rng)?
+        {
+            Action::Transfer(card) =>
+            {
+                self.remove_card(card);
+                Some(table::Transfer::Card(card))
+            },
+            Action::ShowTrump(card) => Some(table::Transfer::ShowTrump(card)),
+            _ => None,
+        }
+    }
+
+    /// The bot never cheats, so every suspicious card is played by somebody else
+    fn challenge_cheat(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        table.suspicious_cards().first().copied()
+    }
+}
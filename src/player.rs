@@ -1,10 +1,18 @@
 use std::path::PrefixComponent;
 use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use rand::Rng;
+use rand::seq::SliceRandom;
 
 use crate::utils::*;
 use crate::cards;
 use crate::table;
+use crate::knowledge::Knowledge;
+use crate::state::{Action, Event, Phase};
+use crate::game::{Settings, SettingsBuilder};
+use crate::mcts::{MctsBot, SearchBudget};
+use crate::solver::{Outcome, Solver};
+use crate::advisor::{Advisor, Hint};
 
 pub trait Player
 {
@@ -50,59 +58,150 @@ pub trait Player
     fn show_cards(& self)
     {
         logln!(0, "{}'s cards:", (self.name()));
-        cards::output_cards(& self.cards());
+        cards::output_cards(self.cards());
+    }
+
+    // --- known information ---
+
+    /// Trump card is public, so every player knows who took it from the stock (`None` while it's in the stock)
+    fn notice_trump_card(&mut self, trump_card: cards::Card, holder_name: Option<& str>);
+
+    /// Called before the deal with the player's seat, so the player can forget the previous game
+    fn notice_game_start(&mut self, player_index: usize, players_count: usize, settings: & Settings)
+    {
+    }
+
+    /// Every event of the game in order, cards which other players draw from the stock must stay unknown to the player
+    fn notice_event(&mut self, event: & Event)
+    {
     }
 
     // --- playing operations ---
 
-    fn play_attack_card(&mut self, table: & table::Table, is_first_attack: bool) -> Option<cards::Card>;
-    fn play_defense_card(&mut self, table: & table::Table) -> Option<(usize, cards::Card)>;
+    /// Called when the game rejects player's move, the rejected card is already returned to the hand
+    fn notice_illegal_move(&mut self, error: & Error)
+    {
+    }
+
+    fn play_attack_card(&mut self, table: & table::Table, is_first_attack: bool, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>;
+    fn play_defense_card(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<(usize, cards::Card)>;
+
+    /// Assignment of defense cards to all unbeaten attack cards, `None` means player takes the cards.
+    /// By default player beats attack cards one by one and takes them all if he/she can't beat one of them.
+    fn play_defense_cards(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<Vec<(usize, cards::Card)>>
+    {
+        let mut table = table.clone();
+        let mut defenses = vec![];
+        while !table.is_attack_beaten()
+        {
+            let (attack_card_index, card) = match self.play_defense_card(& table, rng)
+            {
+                Some(defense) => defense,
+                None =>
+                {
+                    self.cards_mut().extend(defenses.into_iter().map(|(_, card)| card));
+                    self.cards_mut().sort();
+                    return None;
+                },
+            };
+
+            defenses.push((attack_card_index, card));
+            match table.check_defense_card(& card, attack_card_index)
+            {
+                Ok(()) | Err(Error::IncorrectDefense) => table.take_defense_card(card, attack_card_index),
+                // game rejects the whole assignment
+                Err(_) => break,
+            }
+        }
+        Some(defenses)
+    }
+
+    /// Called after the defender decided to take the cards, returns a card of played value for him/her to take as well,
+    /// `None` means player passes
+    fn play_card_to_take(&mut self, table: & table::Table, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>;
+    /// Only for perevodnoy: called before defense, `None` means player will defend
+    fn play_transfer_card(&mut self, table: & table::Table, next_defender_cards_count: usize, can_show_trump: bool, rng: &mut GameRng) -> Option<table::Transfer>;
+    /// Only when cheats are allowed: called before the bout ends, returns the card on the table accused of being played illegally
+    fn challenge_cheat(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<cards::Card>;
 }
 
 pub struct RealPlayer
  {
-    cards : Vec<cards::Card>,   
-    name  : String,
+    cards      : Vec<cards::Card>,   
+    name       : String,
+    trump_card : Option<cards::Card>,
+    /// Suggests moves when the player types 'hint'
+    advisor    : Option<Advisor>,
 }
 
 impl RealPlayer
 {
     pub fn new(name: &str) -> Self
     {
-        Self {cards: Vec::with_capacity(cards::CARDS_IN_DECK_COUNT), name: name.to_string()} 
+        Self {cards: Vec::with_capacity(cards::CARDS_IN_DECK_COUNT), name: name.to_string(), trump_card: None, advisor: None} 
     }
-}
 
-impl Player for RealPlayer
-{
-    fn name(& self) -> &str
+    /// Hints for this player only, settings may enable them for every human player
+    pub fn advisor(mut self, advisor: Advisor) -> Self
     {
-        & self.name
+        self.advisor = Some(advisor);
+        self
     }
 
-    fn cards(& self) -> & Vec<cards::Card>
+    pub fn has_advisor(& self) -> bool
     {
-        & self.cards
+        self.advisor.is_some()
     }
 
-    fn cards_mut(&mut self) -> &mut Vec<cards::Card>
+    fn show_cards_and_trump(& self)
     {
-        &mut self.cards
+        if let Some(trump_card) = self.trump_card
+        {
+            logln!(1, "Trump card: {}", trump_card);
+        }
+        self.show_cards();
+        if self.advisor.is_some()
+        {
+            logln!(1, "Type 'hint' to get advice");
+        }
     }
 
-    fn cards_count(& self) -> usize
+    /// Numbers of cards in hand which can be played legally
+    fn show_playable_cards(& self, playable_cards: & [cards::Card])
     {
-        self.cards.len()
+        let card_numbers: Vec<String> = self.cards.iter().enumerate()
+            .filter(|(_, card)| playable_cards.contains(card))
+            .map(|(card_index, _)| card_index.to_string())
+            .collect();
+        if card_numbers.is_empty()
+        {
+            logln!(1, "You have no playable cards");
+        }
+        else
+        {
+            logln!(1, "Playable cards: {}", (card_numbers.join(", ")));
+        }
     }
 
-    fn play_attack_card(&mut self, table: & table::Table, is_first_attack: bool) -> Option<cards::Card>
+    fn show_hint(&mut self, advise: impl FnOnce(&mut Advisor, & [cards::Card]) -> Hint)
     {
-        self.show_cards();
+        if let Some(advisor) = self.advisor.as_mut()
+        {
+            logln!(2, "Hint: {}", (advise(advisor, & self.cards)));
+        }
+    }
+
+    fn play_attack(&mut self, table: & table::Table, is_first_attack: bool, is_defender_taking: bool, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        self.show_cards_and_trump();
+        self.show_playable_cards(& table.legal_attacks(& self.cards, defender_cards_count));
         let card_index =
             loop
             {
                 match get_input(1, if is_first_attack {"Choose the attack card: "} else {"Choose the attack card (or type 'pass'): "})
                 {
+                    Input::String(string) if string == "hint" && self.advisor.is_some() =>
+                        self.show_hint(|advisor, hand| advisor.advise_attack(hand, table, is_first_attack, is_defender_taking, defender_cards_count)),
                     Input::String(string) => 
                         if !is_first_attack && string == "pass"
                         {
@@ -127,15 +226,87 @@ impl Player for RealPlayer
         logln!();
         Some(self.cards.remove(card_index))
     }
+}
+
+impl Player for RealPlayer
+{
+    fn name(& self) -> &str
+    {
+        & self.name
+    }
+
+    fn cards(& self) -> & Vec<cards::Card>
+    {
+        & self.cards
+    }
+
+    fn cards_mut(&mut self) -> &mut Vec<cards::Card>
+    {
+        &mut self.cards
+    }
+
+    fn cards_count(& self) -> usize
+    {
+        self.cards.len()
+    }
+
+    fn notice_trump_card(&mut self, trump_card: cards::Card, holder_name: Option<& str>)
+    {
+        self.trump_card = Some(trump_card);
+        if let Some(advisor) = self.advisor.as_mut()
+        {
+            advisor.notice_trump_card(trump_card, holder_name);
+        }
+    }
+
+    fn notice_game_start(&mut self, player_index: usize, players_count: usize, settings: & Settings)
+    {
+        if settings.hints() && self.advisor.is_none()
+        {
+            self.advisor = Some(Advisor::new());
+        }
+        if let Some(advisor) = self.advisor.as_mut()
+        {
+            advisor.notice_game_start(player_index, players_count, settings);
+        }
+    }
+
+    fn notice_event(&mut self, event: & Event)
+    {
+        if let Some(advisor) = self.advisor.as_mut()
+        {
+            advisor.notice_event(event);
+        }
+    }
+
+    fn notice_illegal_move(&mut self, error: & Error)
+    {
+        logln!(2, "{}", error);
+    }
+
+    fn play_attack_card(&mut self, table: & table::Table, is_first_attack: bool, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        self.play_attack(table, is_first_attack, false, defender_cards_count, rng)
+    }
     
-    fn play_defense_card(&mut self, table: & table::Table) -> Option<(usize, cards::Card)>
+    fn play_card_to_take(&mut self, table: & table::Table, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
     {
-        self.show_cards();
+        logln!(1, "Defender is taking the cards, you can add more cards of played values");
+        self.play_attack(table, false, true, defender_cards_count, rng)
+    }
+    
+    fn play_defense_card(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<(usize, cards::Card)>
+    {
+        self.show_cards_and_trump();
+        let defenses: Vec<cards::Card> = table.legal_defenses(& self.cards).into_iter().map(|(_, card)| card).collect();
+        self.show_playable_cards(& defenses);
         let defense_card_index =
             loop
             {
                 match get_input(1, "Choose the defense card (or type 'take'): ")
                 {
+                    Input::String(string) if string == "hint" && self.advisor.is_some() =>
+                        self.show_hint(|advisor, hand| advisor.advise_defense(hand, table)),
                     Input::String(string) => 
                         if string == "take"
                         {
@@ -161,6 +332,8 @@ impl Player for RealPlayer
         {
             match get_input(1, "Choose card to beat (or type 'take'): ")
             {
+                Input::String(string) if string == "hint" && self.advisor.is_some() =>
+                    self.show_hint(|advisor, hand| advisor.advise_defense(hand, table)),
                 Input::String(string) => 
                     if string == "take"
                     {
@@ -171,34 +344,135 @@ impl Player for RealPlayer
                         logln!(2, "Inrecognized string answer");
                     },
                 Input::Number(index) =>
-                    if index < table.attack_cards().len()
+                    match table.check_defense_card(& self.cards[defense_card_index], index)
+                    {
+                        Ok(()) => return Some((index, self.cards.remove(defense_card_index))),
+                        Err(Error::IncorrectDefense) if table.cheats_allowed() => return Some((index, self.cards.remove(defense_card_index))),
+                        Err(Error::IncorrectDefense) =>
+                            logln!(2, "You can't beat {} with {}", (table.played_cards()[index].0), (self.cards[defense_card_index])),
+                        Err(error) => logln!(2, "{}", error),
+                    },
+            }
+        };
+    }
+
+    fn play_transfer_card(&mut self, table: & table::Table, next_defender_cards_count: usize, can_show_trump: bool, rng: &mut GameRng) -> Option<table::Transfer>
+    {
+        self.show_cards_and_trump();
+        loop
+        {
+            match get_input(1, "Choose the card to transfer attack (or type 'defend'): ")
+            {
+                Input::String(string) if string == "hint" && self.advisor.is_some() =>
+                    self.show_hint(|advisor, hand| advisor.advise_transfer(hand, table, next_defender_cards_count, can_show_trump)),
+                Input::String(string) => 
+                    if string == "defend"
+                    {
+                        return None;
+                    }
+                    else
+                    {
+                        logln!(2, "Inrecognized string answer");
+                    },
+                Input::Number(index) =>
+                    if index < self.cards_count()
                     {
-                        let defense_card = & self.cards[defense_card_index];
-                        let attack_card = & table.attack_cards()[index];
-                        if table.can_beat(defense_card, index)
+                        let mut transfer = table::Transfer::Card(self.cards[index]);
+                        if can_show_trump && self.cards[index].suit() == table.trump() && !self.cards[index].is_joker()
                         {
-                            return Some((index, self.cards.remove(defense_card_index)));
+                            loop
+                            {
+                                match get_input(1, "Lay the trump or just show it? (type 'lay' or 'show'): ")
+                                {
+                                    Input::String(string) if string == "lay" => break,
+                                    Input::String(string) if string == "show" =>
+                                    {
+                                        transfer = table::Transfer::ShowTrump(self.cards[index]);
+                                        break;
+                                    },
+                                    _ => logln!(2, "Inrecognized answer"),
+                                }
+                            }
                         }
-                        else
+
+                        match table.check_transfer(& transfer, next_defender_cards_count)
                         {
-                            logln!(2, "You can't beat {defense_card} with {attack_card}");    
+                            Ok(()) =>
+                            {
+                                if let table::Transfer::Card(_) = transfer
+                                {
+                                    self.cards.remove(index);
+                                }
+                                logln!();
+                                return Some(transfer);
+                            },
+                            Err(error) => logln!(2, "{}", error),
                         }
                     }
                     else
                     {
-                        logln!(2, "There are only {} attack cards on the table", (table.attack_cards().len()));
+                        logln!(2, "You have only {} cards", (self.cards_count()));
                     },
             }
+        }
+    }
+
+    fn challenge_cheat(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        logln!(0, "{}", table);
+        let slot_index =
+            loop
+            {
+                match get_input(1, "Choose the card number to accuse of cheating (or type 'no'): ")
+                {
+                    Input::String(string) => 
+                        if string == "no"
+                        {
+                            return None;
+                        }
+                        else
+                        {
+                            logln!(2, "Inrecognized string answer");
+                        },
+                    Input::Number(index) =>
+                        if index < table.attack_cards().len()
+                        {
+                            break index;
+                        }
+                        else
+                        {
+                            logln!(2, "There are only {} attack cards on the table", (table.attack_cards().len()));
+                        },
+                }
+            };
+
+        let (attack_card, defense_card) = table.played_cards()[slot_index];
+        let defense_card = match defense_card
+        {
+            Some(defense_card) => defense_card,
+            None => return Some(attack_card),
         };
+
+        loop
+        {
+            match get_input(1, "Accuse the attack or the defense card? (type 'attack' or 'defense'): ")
+            {
+                Input::String(string) if string == "attack" => return Some(attack_card),
+                Input::String(string) if string == "defense" => return Some(defense_card),
+                _ => logln!(2, "Inrecognized answer"),
+            }
+        }
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BotDificulty
 {
     Easy,
     Medium,
     Hard,
+    /// Played by `MctsBot`, plain `Bot` plays it as hard
+    Expert,
 }
 
 impl std::fmt::Display for BotDificulty
@@ -211,26 +485,268 @@ impl std::fmt::Display for BotDificulty
                 Self::Easy => "Easy",
                 Self::Medium => "Medium",
                 Self::Hard => "Hard",
+                Self::Expert => "Expert",
             })
     }
 }
 
+/// Bots of all kinds are numbered in order of creation
+pub fn next_bot_number() -> usize
+{
+    static BOT_COUNT: AtomicUsize = AtomicUsize::new(0);
+    BOT_COUNT.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Expert bots search for moves with simulations within the budget, the others follow their strategies
+pub fn new_bot(difficulty: BotDificulty, expert_budget: SearchBudget) -> Box<dyn Player>
+{
+    match difficulty
+    {
+        BotDificulty::Expert => Box::new(MctsBot::new().budget(expert_budget)),
+        _ => Box::new(Bot::new(difficulty)),
+    }
+}
+
 pub struct Bot
  {
-    cards          : Vec<cards::Card>,   
-    name           : String,
-    bot_difficulty : BotDificulty,
+    cards             : Vec<cards::Card>,   
+    name              : String,
+    bot_difficulty    : BotDificulty,
+    trump_card        : Option<cards::Card>,
+    trump_card_holder : Option<String>,
+    delay             : Duration,
+    cheated_cards     : Vec<cards::Card>,
+    settings          : Settings,
+    knowledge         : Knowledge,
+    /// Keeps solved positions between decisions of the endgame
+    solver            : Solver,
 }
 
 impl Bot
 {
+    /// Probabilities of cheating when there is no legal move and of accusing suspicious or random cards
+    const CHEAT_PROBABILITY: f64 = 0.2;
+    const ACCUSE_PROBABILITY: f64 = 0.7;
+    const FALSE_ACCUSE_PROBABILITY: f64 = 0.05;
+    /// Endgames with more cards or which take longer to solve are played by heuristics
+    const ENDGAME_MAX_CARDS_COUNT: usize = 10;
+    const ENDGAME_MAX_NODES_COUNT: usize = 5_000;
+
     pub fn new(difficulty: BotDificulty) -> Self
     {
-        static mut BOT_COUNT: u8 = 0;
-        unsafe 
+        let bot_number = next_bot_number();
+        Self {cards: vec![], name: format!("Bot #{bot_number} ({difficulty})"), bot_difficulty: difficulty, trump_card: None, trump_card_holder: None,
+            delay: Duration::from_millis(500), cheated_cards: vec![], settings: SettingsBuilder::new().build(), knowledge: Knowledge::default(),
+            solver: Solver::new().max_nodes_count(Self::ENDGAME_MAX_NODES_COUNT)}
+    }
+
+    /// Index of the lowest card which is neither trump nor joker
+    fn lowest_plain_card_index(& self, trump: cards::Suit) -> Option<usize>
+    {
+        (0 .. self.cards_count())
+            .filter(|index| self.cards[*index].suit() != trump && !self.cards[*index].is_joker())
+            .min_by_key(|index| self.cards[*index].value())
+    }
+
+    fn cheat(&mut self, card_index: usize) -> cards::Card
+    {
+        let card = self.cards.remove(card_index);
+        self.cheated_cards.push(card);
+        card
+    }
+
+    /// Pause before each move, so people can follow the game
+    pub fn delay(mut self, delay: Duration) -> Self
+    {
+        self.delay = delay;
+        self
+    }
+
+    // --- strategies ---
+
+    /// Hard bot plays perfectly once it knows all the cards, unless the endgame is lost anyway:
+    /// then heuristics give the opponent more chances to make a mistake
+    fn solve_endgame(&mut self, table: & table::Table, phase: Phase) -> Option<Action>
+    {
+        let cards_count: usize = (0 .. self.knowledge.players_count()).map(|player_index| self.knowledge.hand_size(player_index)).sum();
+        if !matches!(self.bot_difficulty, BotDificulty::Hard | BotDificulty::Expert) || !self.knowledge.is_everything_known(table)
+            || cards_count > Self::ENDGAME_MAX_CARDS_COUNT
         {
-            BOT_COUNT += 1;
-            Self {cards: vec![], name: format!("Bot #{BOT_COUNT} ({difficulty})"), bot_difficulty: difficulty} 
+            return None;
+        }
+
+        let state = self.knowledge.state(& self.settings, & self.cards, table, phase, self.knowledge.unknown_cards().clone())?;
+        self.solver.solve(& state, self.knowledge.player_index()).ok()
+            .filter(|solution| solution.outcome > Outcome::Loss)
+            .and_then(|solution| solution.best_action)
+    }
+
+    /// Cards which may be in opponents' hands with their likelihood weights: cards known to be there
+    /// weigh twice as much as unknown ones
+    fn hidden_cards(& self) -> Vec<(cards::Card, usize)>
+    {
+        self.knowledge.opponents_cards().into_iter()
+            .map(|(card, is_known)| (card, if is_known {2} else {1}))
+            .collect()
+    }
+
+    /// Number of cards the defender may beat the card with
+    fn beaters_count(& self, table: & table::Table, card: & cards::Card) -> usize
+    {
+        self.knowledge.defending_index()
+            .map_or(0, |defending_index| self.knowledge.possible_cards(defending_index).iter().filter(|defense_card| table.beats(defense_card, card)).count())
+    }
+
+    /// Likelihood that opponents can throw in cards of the value
+    fn same_value_weight(hidden_cards: & [(cards::Card, usize)], value: cards::Value) -> usize
+    {
+        hidden_cards.iter().filter(|(hidden_card, _)| hidden_card.value() == value).map(|(_, weight)| weight).sum()
+    }
+
+    /// Easy bot picks any legal card or passes when it's allowed
+    fn random_card(cards: & [cards::Card], can_pass: bool, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        let moves_count = cards.len() + usize::from(can_pass);
+        if moves_count == 0
+        {
+            return None;
+        }
+        cards.get(rng.gen_range(0 .. moves_count)).copied()
+    }
+
+    /// `None` means pass
+    fn choose_attack_card(& self, table: & table::Table, legal_attacks: Vec<cards::Card>, is_first_attack: bool, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        let trump = table.trump();
+        let is_stock_empty = table.remain_cards_count() == 0;
+        match self.bot_difficulty
+        {
+            BotDificulty::Easy => Self::random_card(& legal_attacks, !is_first_attack, rng),
+            // jokers are saved for defense and trumps are thrown in only when the stock is over,
+            // among cards of the same value the one which the defender is less likely to beat is chosen
+            BotDificulty::Medium => legal_attacks.into_iter()
+                .filter(|card| !card.is_joker() && (is_first_attack || is_stock_empty || card.suit() != trump))
+                .min_by_key(|card| (card.weight(trump), self.beaters_count(table, card))),
+            BotDificulty::Hard | BotDificulty::Expert =>
+            {
+                let hidden_cards = self.hidden_cards();
+                let attack_cards = legal_attacks.into_iter().filter(|card| !card.is_joker() && (is_first_attack || is_stock_empty || card.suit() != trump));
+
+                // when the stock is over and there is one opponent left, all defender's cards are known
+                if is_first_attack && is_stock_empty && hidden_cards.len() == defender_cards_count
+                {
+                    let defender_cards: Vec<cards::Card> = hidden_cards.iter().map(|(card, _)| *card).collect();
+                    return attack_cards.min_by_key(|card|
+                    {
+                        let (cards_left_count, is_taken) = self.play_out_bout(table, & defender_cards, *card);
+                        (cards_left_count > 0, !is_taken, cards_left_count, card.weight(trump))
+                    });
+                }
+
+                // cards of values which the bot can throw in again are preferred
+                attack_cards.min_by_key(|card|
+                    (card.weight(trump), usize::MAX - self.cards.iter().filter(|hand_card| hand_card.value() == card.value()).count()))
+            },
+        }
+    }
+
+    /// Lookahead of the bout against the defender whose cards are known: the defender beats attack cards with the cheapest cards,
+    /// the bot throws in the cheapest cards it can. Returns number of cards left in the bot's hand and whether the defender takes
+    fn play_out_bout(& self, table: & table::Table, defender_cards: & [cards::Card], attack_card: cards::Card) -> (usize, bool)
+    {
+        let trump = table.trump();
+        let mut table = table.clone();
+        let mut hand = self.cards.clone();
+        let mut defender_cards = defender_cards.to_vec();
+        let mut attack_card = Some(attack_card);
+        let mut is_taken = false;
+        while let Some(card) = attack_card
+        {
+            table.take_attack_card(card);
+            hand.retain(|hand_card| *hand_card != card);
+
+            let attack_card_index = table.played_cards().len() - 1;
+            let defense_card = defender_cards.iter().filter(|defense_card| table.can_beat(defense_card, attack_card_index))
+                .min_by_key(|defense_card| defense_card.weight(trump))
+                .copied();
+            match defense_card
+            {
+                // the taking defender gets all the cards the bot can throw in
+                _ if is_taken => {},
+                Some(defense_card) =>
+                {
+                    table.take_defense_card(defense_card, attack_card_index);
+                    defender_cards.retain(|card| *card != defense_card);
+                },
+                None => is_taken = true,
+            }
+
+            attack_card = table.legal_attacks(& hand, defender_cards.len()).into_iter()
+                .min_by_key(|card| card.weight(trump));
+        }
+        (hand.len(), is_taken)
+    }
+
+    /// `None` means take
+    fn choose_defense(& self, table: & table::Table, defenses: Vec<(usize, cards::Card)>, rng: &mut GameRng) -> Option<(usize, cards::Card)>
+    {
+        let trump = table.trump();
+        match self.bot_difficulty
+        {
+            BotDificulty::Easy =>
+            {
+                let index = rng.gen_range(0 ..= defenses.len());
+                defenses.get(index).copied()
+            },
+            BotDificulty::Medium => defenses.into_iter().min_by_key(|(_, card)| card.weight(trump)),
+            BotDificulty::Hard | BotDificulty::Expert =>
+            {
+                // cards of values which opponents hardly have can't be followed by throw-ins
+                let hidden_cards = self.hidden_cards();
+                defenses.into_iter().min_by_key(|(_, card)| (card.weight(trump), Self::same_value_weight(& hidden_cards, card.value())))
+            },
+        }
+    }
+
+    fn choose_transfer(& self, table: & table::Table, next_defender_cards_count: usize, can_show_trump: bool, rng: &mut GameRng) -> Option<table::Transfer>
+    {
+        let trump = table.trump();
+        let mut transfers: Vec<table::Transfer> = self.cards.iter()
+            .filter(|card| !card.is_joker())
+            .flat_map(|card| [table::Transfer::Card(*card)].into_iter()
+                .chain(Some(table::Transfer::ShowTrump(*card)).filter(|_| can_show_trump && card.suit() == trump)))
+            .filter(|transfer| table.check_transfer(transfer, next_defender_cards_count).is_ok())
+            .collect();
+
+        if self.bot_difficulty == BotDificulty::Easy
+        {
+            let index = rng.gen_range(0 ..= transfers.len());
+            return transfers.get(index).copied();
+        }
+
+        // trumps aren't laid, but showing trump keeps the card, so it is preferred to laying a plain one
+        transfers.retain(|transfer| !matches!(transfer, table::Transfer::Card(card) if card.suit() == trump));
+        transfers.iter().find(|transfer| matches!(transfer, table::Transfer::ShowTrump(_)))
+            .or_else(|| transfers.first())
+            .copied()
+    }
+
+    /// `None` means pass
+    fn choose_card_to_take(& self, table: & table::Table, legal_cards: Vec<cards::Card>, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        let trump = table.trump();
+        match self.bot_difficulty
+        {
+            BotDificulty::Easy => Self::random_card(& legal_cards, true, rng),
+            // trumps and jokers aren't given away
+            BotDificulty::Medium => legal_cards.into_iter()
+                .filter(|card| card.suit() != trump && !card.is_joker())
+                .min_by_key(|card| card.value()),
+            // in the endgame any card the bot gets rid of brings it closer to the win
+            BotDificulty::Hard | BotDificulty::Expert => legal_cards.into_iter()
+                .filter(|card| !card.is_joker())
+                .filter(|card| table.remain_cards_count() == 0 || card.suit() != trump)
+                .min_by_key(|card| card.weight(trump)),
         }
     }
 }
@@ -257,58 +773,146 @@ impl Player for Bot
         self.cards.len()
     }
 
-    fn play_attack_card(&mut self, table: & table::Table, is_first_attack: bool) -> Option<cards::Card>
+    fn notice_trump_card(&mut self, trump_card: cards::Card, holder_name: Option<& str>)
+    {
+        self.knowledge.notice_trump_card(trump_card);
+        self.trump_card = Some(trump_card);
+        self.trump_card_holder = holder_name.map(str::to_string);
+    }
+
+    fn notice_game_start(&mut self, player_index: usize, players_count: usize, settings: & Settings)
+    {
+        self.settings = *settings;
+        self.knowledge = Knowledge::new(player_index, players_count, settings.card_deck());
+        self.solver = Solver::new().max_nodes_count(Self::ENDGAME_MAX_NODES_COUNT);
+    }
+
+    fn notice_event(&mut self, event: & Event)
+    {
+        self.knowledge.notice_event(event);
+    }
+
+    fn play_attack_card(&mut self, table: & table::Table, is_first_attack: bool, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
     {
-        std::thread::sleep(Duration::from_millis(500));
-        
-        let lowest_cards_indecies: [usize; 3] = [0; 3]; // trump isn't taken
-        for i in 0 .. self.cards_count()
+        std::thread::sleep(self.delay);
+
+        match self.solve_endgame(table, if is_first_attack {Phase::Attack} else {Phase::ThrowIn})
         {
-            match table.check_attack_card(& self.cards[i], is_first_attack)
+            Some(Action::Attack(card) | Action::ThrowIn(card)) =>
             {
-                Ok(()) => return Some(self.cards.remove(i)),
-                Err(Error::AbsentCardValue(_)) => continue,
-                Err(error) => panic!("Bot attack error: {error}"),
-            }
-        };
+                self.cards.retain(|hand_card| *hand_card != card);
+                return Some(card);
+            },
+            Some(Action::Pass) => return None,
+            _ => (),
+        }
+
+        let legal_attacks = table.legal_attacks(& self.cards, defender_cards_count);
+        if let Some(card) = self.choose_attack_card(table, legal_attacks, is_first_attack, defender_cards_count, rng)
+        {
+            self.cards.retain(|hand_card| *hand_card != card);
+            return Some(card);
+        }
+
+        // only jokers are left to start attack
+        if is_first_attack
+        {
+            return Some(self.cards.remove(0));
+        }
+
+        if table.cheats_allowed() && !table.is_attack_finished(defender_cards_count) && rng.gen_bool(Self::CHEAT_PROBABILITY)
+        {
+            return self.lowest_plain_card_index(table.trump()).map(|index| self.cheat(index));
+        }
         None
     }
     
-    fn play_defense_card(&mut self, table: & table::Table) -> Option<(usize, cards::Card)>
+    fn play_card_to_take(&mut self, table: & table::Table, defender_cards_count: usize, rng: &mut GameRng) -> Option<cards::Card>
     {
-        std::thread::sleep(Duration::from_millis(500));
+        std::thread::sleep(self.delay);
 
-        let mut non_trump_index: Option<usize> = None;
-        let mut trump_index: Option<usize> = None;
-        
-        let attack_card_index = table.defense_cards().len();
-        for defense_card_index in (0 .. self.cards_count()).rev()
+        let card = match self.solve_endgame(table, Phase::ThrowIn)
         {
-            match table.check_defense_card(& self.cards[defense_card_index], attack_card_index)
+            Some(Action::ThrowIn(card)) => card,
+            Some(Action::Pass) => return None,
+            _ =>
             {
-                Ok(()) =>
-                    if self.cards[defense_card_index].suit() == table.trump()
-                    {
-                        trump_index = Some(defense_card_index);
-                    }
-                    else
-                    {
-                        non_trump_index = Some(defense_card_index);
-                    },
-                Err(Error::IncorrectDefense) => continue,
-                Err(error) => panic!("{} defense error: {error}", self.name)
-            }
+                let legal_cards = table.legal_attacks(& self.cards, defender_cards_count);
+                self.choose_card_to_take(table, legal_cards, rng)?
+            },
+        };
+        self.cards.retain(|hand_card| *hand_card != card);
+        Some(card)
+    }
+    
+    fn play_defense_card(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<(usize, cards::Card)>
+    {
+        std::thread::sleep(self.delay);
+
+        match self.solve_endgame(table, Phase::Defense)
+        {
+            Some(Action::Defend(attack_card_index, card)) =>
+            {
+                self.cards.retain(|hand_card| *hand_card != card);
+                return Some((attack_card_index, card));
+            },
+            Some(Action::Take) => return None,
+            _ => (),
         }
-        
-        match non_trump_index   
+
+        let defenses = table.legal_defenses(& self.cards);
+        let can_defend = !defenses.is_empty();
+        if let Some((attack_card_index, card)) = self.choose_defense(table, defenses, rng)
         {
-            Some(index) => Some((attack_card_index, self.cards.remove(index))),
-            None =>
-                match trump_index
-                {
-                    Some(index) => Some((attack_card_index, self.cards.remove(index))),
-                    None => None
-                }
+            self.cards.retain(|hand_card| *hand_card != card);
+            return Some((attack_card_index, card));
+        }
+
+        let attack_card_index = table.played_cards().iter().position(|(_, defense_card)| defense_card.is_none());
+        if let Some(attack_card_index) = attack_card_index.filter(|_| !can_defend && table.cheats_allowed() && rng.gen_bool(Self::CHEAT_PROBABILITY))
+        {
+            return self.lowest_plain_card_index(table.trump()).map(|index| (attack_card_index, self.cheat(index)));
+        }
+        None
+    }
+
+    fn play_transfer_card(&mut self, table: & table::Table, next_defender_cards_count: usize, can_show_trump: bool, rng: &mut GameRng) -> Option<table::Transfer>
+    {
+        std::thread::sleep(self.delay);
+
+        let transfer = match self.solve_endgame(table, Phase::Defense)
+        {
+            Some(Action::Transfer(card)) => table::Transfer::Card(card),
+            Some(Action::ShowTrump(card)) => table::Transfer::ShowTrump(card),
+            Some(_) => return None,
+            None => self.choose_transfer(table, next_defender_cards_count, can_show_trump, rng)?,
+        };
+        if let table::Transfer::Card(card) = transfer
+        {
+            self.cards.retain(|hand_card| *hand_card != card);
+        }
+        Some(transfer)
+    }
+
+    fn challenge_cheat(&mut self, table: & table::Table, rng: &mut GameRng) -> Option<cards::Card>
+    {
+        let cheated_cards = std::mem::take(&mut self.cheated_cards);
+        let suspicious_cards: Vec<cards::Card> = table.suspicious_cards().into_iter()
+            .filter(|card| !cheated_cards.contains(card))
+            .collect();
+
+        if !suspicious_cards.is_empty() && rng.gen_bool(Self::ACCUSE_PROBABILITY)
+        {
+            return Some(suspicious_cards[0]);
         }
+
+        if rng.gen_bool(Self::FALSE_ACCUSE_PROBABILITY)
+        {
+            let played_cards: Vec<cards::Card> = table.attack_cards().into_iter().chain(table.defense_cards())
+                .filter(|card| !cheated_cards.contains(card))
+                .collect();
+            return played_cards.choose(rng).copied();
+        }
+        None
     }
-}
\ No newline at end of file
+}
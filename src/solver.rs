@@ -0,0 +1,339 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::cards;
+use crate::game::Settings;
+use crate::state::{Action, GameState, Phase, PositionKey, RoundPosition};
+use crate::table::Table;
+use crate::utils::*;
+
+/// Result of the game for the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Outcome
+{
+    Loss,
+    Draw,
+    Win,
+}
+
+impl std::fmt::Display for Outcome
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        write!(f, "{}", match self
+            {
+                Self::Loss => "loses",
+                Self::Draw => "draws",
+                Self::Win  => "wins",
+            })
+    }
+}
+
+/// Stored outcome is exact or only a bound when the search was cut
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Bound
+{
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Transposition
+{
+    outcome     : Outcome,
+    bound       : Bound,
+    best_action : Option<Action>,
+}
+
+/// Result of the best play of both players
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Solution
+{
+    pub outcome     : Outcome,
+    /// `None` when the game is over
+    pub best_action : Option<Action>,
+}
+
+/// Minimax search with alpha-beta pruning and a transposition table for the endgame:
+/// the stock is over, so two players left know each other's cards. Positions are stored whole, so different positions never share an outcome
+pub struct Solver
+{
+    transpositions  : HashMap<PositionKey, Transposition>,
+    /// Positions of the current line, players who repeat a position go around in circles, which is a draw
+    line            : HashSet<PositionKey>,
+    player_index    : usize,
+    nodes_count     : usize,
+    max_nodes_count : usize,
+}
+
+impl Solver
+{
+    pub const DEFAULT_MAX_NODES_COUNT: usize = 1_000_000;
+
+    pub fn new() -> Self
+    {
+        Self
+        {
+            transpositions  : HashMap::new(),
+            line            : HashSet::new(),
+            player_index    : 0,
+            nodes_count     : 0,
+            max_nodes_count : Self::DEFAULT_MAX_NODES_COUNT,
+        }
+    }
+
+    /// Search gives up after visiting given number of positions
+    pub fn max_nodes_count(mut self, max_nodes_count: usize) -> Self
+    {
+        self.max_nodes_count = max_nodes_count;
+        self
+    }
+
+    /// Positions visited by the last search
+    pub fn nodes_count(& self) -> usize
+    {
+        self.nodes_count
+    }
+
+    /// Outcome for the player and his/her best action if it's his/her turn, otherwise the opponent's best reply
+    pub fn solve(&mut self, state: & GameState, player_index: usize) -> Result<Solution, Error>
+    {
+        self.solve_after(state, player_index, & [])
+    }
+
+    /// Same as `solve`, but returning to any of the earlier positions of the game is going around in circles as well
+    pub fn solve_after(&mut self, state: & GameState, player_index: usize, previous_states: & [GameState]) -> Result<Solution, Error>
+    {
+        if !state.is_game_over() && (state.table().remain_cards_count() > 0 || state.active_players().len() != 2)
+        {
+            return Err(Error::NotEndgame);
+        }
+
+        // stored outcomes are for the same player
+        if player_index != self.player_index
+        {
+            self.transpositions.clear();
+            self.player_index = player_index;
+        }
+        self.line = previous_states.iter().map(GameState::position_key).collect();
+        self.nodes_count = 0;
+        let (outcome, best_action, _) = self.search(state, Outcome::Loss, Outcome::Win)?;
+        Ok(Solution {outcome, best_action})
+    }
+
+    fn outcome(& self, state: & GameState) -> Outcome
+    {
+        match (state.is_draw(), state.has_lost(self.player_index))
+        {
+            (true, _) => Outcome::Draw,
+            (false, true) => Outcome::Loss,
+            (false, false) => Outcome::Win,
+        }
+    }
+
+    /// Actions which are likely the best go first to cut more: the stored best one, then the lowest cards, taking and passing are the last.
+    /// Accusations are never right, since cheating moves aren't searched
+    fn ordered_actions(state: & GameState, best_action: Option<Action>) -> Vec<Action>
+    {
+        if let Phase::Challenge(_) = state.phase()
+        {
+            return vec![Action::Pass];
+        }
+
+        let trump = state.table().trump();
+        let mut actions = state.legal_actions();
+        actions.sort_by_key(|action| match action
+        {
+            action if Some(*action) == best_action => 0,
+            Action::Attack(card) | Action::ThrowIn(card) | Action::Defend(_, card) | Action::Transfer(card) | Action::ShowTrump(card) => 1 + card.weight(trump),
+            _ => usize::MAX,
+        });
+        actions
+    }
+
+    /// Outcome with the best action and whether the outcome depends on the line which led to the position:
+    /// such outcomes came from repetitions and aren't stored
+    fn search(&mut self, state: & GameState, mut alpha: Outcome, mut beta: Outcome) -> Result<(Outcome, Option<Action>, bool), Error>
+    {
+        let current_index = match state.current_player()
+        {
+            Some(current_index) => current_index,
+            None => return Ok((self.outcome(state), None, false)),
+        };
+
+        self.nodes_count += 1;
+        if self.nodes_count > self.max_nodes_count
+        {
+            return Err(Error::SolverLimitExceeded(self.max_nodes_count));
+        }
+
+        let key = state.position_key();
+        if self.line.contains(& key)
+        {
+            return Ok((Outcome::Draw, None, true));
+        }
+
+        let mut stored_action = None;
+        if let Some(transposition) = self.transpositions.get(& key)
+        {
+            match transposition.bound
+            {
+                Bound::Exact => return Ok((transposition.outcome, transposition.best_action, false)),
+                Bound::Lower => alpha = alpha.max(transposition.outcome),
+                Bound::Upper => beta = beta.min(transposition.outcome),
+            }
+            if alpha >= beta
+            {
+                return Ok((transposition.outcome, transposition.best_action, false));
+            }
+            stored_action = transposition.best_action;
+        }
+
+        let (initial_alpha, initial_beta) = (alpha, beta);
+        let is_maximizing = current_index == self.player_index;
+        let mut best: Option<(Outcome, Action)> = None;
+        let mut is_line_dependent = false;
+        self.line.insert(key.clone());
+        for action in Self::ordered_actions(state, stored_action)
+        {
+            let mut next_state = state.clone();
+            if next_state.apply(action).is_err()
+            {
+                continue;
+            }
+
+            let (outcome, _, is_outcome_line_dependent) = self.search(& next_state, alpha, beta)?;
+            is_line_dependent |= is_outcome_line_dependent;
+            let is_better = match best
+            {
+                Some((best_outcome, _)) => if is_maximizing {outcome > best_outcome} else {outcome < best_outcome},
+                None => true,
+            };
+            if is_better
+            {
+                best = Some((outcome, action));
+            }
+
+            if is_maximizing {alpha = alpha.max(outcome)} else {beta = beta.min(outcome)}
+            if alpha >= beta
+            {
+                break;
+            }
+        }
+        self.line.remove(& key);
+
+        let (outcome, best_action) = match best
+        {
+            Some((outcome, action)) => (outcome, Some(action)),
+            None => (self.outcome(state), None),
+        };
+        if !is_line_dependent
+        {
+            let bound = if outcome <= initial_alpha {Bound::Upper} else if outcome >= initial_beta {Bound::Lower} else {Bound::Exact};
+            self.transpositions.insert(key, Transposition {outcome, bound, best_action});
+        }
+        Ok((outcome, best_action, is_line_dependent))
+    }
+}
+
+impl Default for Solver
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+fn parse_cards(text: & str) -> Result<Vec<cards::Card>, Error>
+{
+    text.split_whitespace().map(str::parse).collect()
+}
+
+fn invalid_position(reason: String) -> Error
+{
+    Error::InvalidArgument(format!("position: {reason}"))
+}
+
+/// Reads the endgame position of the attacker (player #0) and the defender (player #1) given in lines or separated with ';':
+///
+///     trump: 9S
+///     attacker: 6H 7C KS
+///     defender: 8H AS
+///     table: 7D/9D JC
+///
+/// Table is optional, defense card follows its attack card after '/'. The attacker moves when the table is empty
+/// or every card on it is beaten, otherwise the defender does and the attacker's hand may be empty
+pub fn parse_position(text: & str, settings: & Settings) -> Result<GameState, Error>
+{
+    let mut trump_card = None;
+    let mut hands = vec![vec![], vec![]];
+    let mut played_cards = vec![];
+    for line in text.split([';', '\n']).map(str::trim).filter(|line| !line.is_empty())
+    {
+        let unreadable_line = || invalid_position(format!("can't read '{line}'"));
+        let (key, value) = line.split_once(':').ok_or_else(unreadable_line)?;
+        match key.trim().to_lowercase().as_str()
+        {
+            "trump" => trump_card = Some(value.trim().parse::<cards::Card>()?),
+            "attacker" => hands[0] = parse_cards(value)?,
+            "defender" => hands[1] = parse_cards(value)?,
+            "table" =>
+            {
+                for pair in value.split_whitespace()
+                {
+                    let mut pair_cards = pair.split('/');
+                    let attack_card = pair_cards.next().ok_or_else(unreadable_line)?.parse()?;
+                    let defense_card = pair_cards.next().map(str::parse).transpose()?;
+                    played_cards.push((attack_card, defense_card));
+                }
+            },
+            _ => return Err(unreadable_line()),
+        }
+    }
+
+    let trump_card = trump_card.ok_or_else(|| invalid_position("trump card is missing".to_string()))?;
+
+    let deck_cards = settings.card_deck().cards();
+    let mut all_cards: Vec<cards::Card> = hands.iter().flatten().copied()
+        .chain(played_cards.iter().flat_map(|(attack_card, defense_card)| std::iter::once(*attack_card).chain(*defense_card)))
+        .collect();
+    if let Some(card) = all_cards.iter().chain(std::iter::once(& trump_card)).find(|card| !deck_cards.contains(card))
+    {
+        return Err(invalid_position(format!("{} isn't in the {}-card deck", card.to_string().trim(), settings.card_deck() as usize)));
+    }
+    all_cards.sort();
+    if let Some(card) = all_cards.windows(2).find(|pair| pair[0] == pair[1]).map(|pair| pair[0])
+    {
+        return Err(invalid_position(format!("{} is given twice", card.to_string().trim())));
+    }
+    for hand in hands.iter_mut()
+    {
+        hand.sort();
+    }
+
+    let phase = match played_cards.iter().any(|(_, defense_card)| defense_card.is_none())
+    {
+        true => Phase::Defense,
+        false if played_cards.is_empty() => Phase::Attack,
+        false => Phase::ThrowIn,
+    };
+    // the attacker may have led his/her last card, but the defender still has to answer it
+    match phase
+    {
+        Phase::Defense if hands[1].is_empty() => return Err(invalid_position("defender must have cards".to_string())),
+        Phase::Defense => (),
+        _ if hands.iter().any(Vec::is_empty) => return Err(invalid_position("both players must have cards".to_string())),
+        _ => (),
+    }
+    let mut table = Table::new(settings);
+    table.set_endgame(trump_card, played_cards.clone());
+    for (attack_card, defense_card) in played_cards
+    {
+        if let Some(defense_card) = defense_card.filter(|defense_card| !table.beats(defense_card, & attack_card))
+        {
+            return Err(invalid_position(format!("{} doesn't beat {}", defense_card.to_string().trim(), attack_card.to_string().trim())));
+        }
+    }
+    let round = RoundPosition {first_attacking_index: 0, attacking_index: 0, defending_index: 1, ..Default::default()};
+    Ok(GameState::from_position(settings, table, hands, vec![], phase, round))
+}
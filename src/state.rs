@@ -0,0 +1,932 @@
+use rand::Rng;
+
+use crate::cards;
+use crate::game::{Settings, StartingRule, ThrowInRule, Variant};
+use crate::table::{Table, Transfer};
+use crate::utils::*;
+
+/// Decision of the current player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action
+{
+    /// First card of the bout
+    Attack(cards::Card),
+    ThrowIn(cards::Card),
+    /// Attack card index and defense card
+    Defend(usize, cards::Card),
+    Transfer(cards::Card),
+    /// Trump of the attack value is only shown and stays in hand
+    ShowTrump(cards::Card),
+    /// Attacker doesn't throw in or player doesn't accuse anybody of cheating
+    Pass,
+    Take,
+    /// Card on the table accused of being played illegally
+    Accuse(cards::Card),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardsSource
+{
+    Stock,
+    Table,
+    /// Penalty cards when the stock is empty
+    Discard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartReason
+{
+    /// Player after the previous durak starts
+    AfterDurak(usize),
+    LowestTrump(cards::Card),
+    NoTrumps,
+    Random,
+}
+
+/// Everything that happened after an action, in order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event
+{
+    FirstAttackerChosen { player_index: usize, reason: StartReason },
+    RoundStarted { attacking_index: usize, defending_index: usize },
+    Attacked { player_index: usize, card: cards::Card },
+    ThrewIn { player_index: usize, card: cards::Card },
+    Defended { player_index: usize, attack_card: cards::Card, defense_card: cards::Card },
+    Transferred { player_index: usize, card: cards::Card, defending_index: usize },
+    TrumpShown { player_index: usize, card: cards::Card, defending_index: usize },
+    Passed { player_index: usize },
+    Took { player_index: usize },
+    CheatCaught { accuser_index: usize, cheater_index: usize, card: cards::Card },
+    FalseAccusation { accuser_index: usize, card: cards::Card },
+    BoutEnded { defending_index: usize, is_defense_succeed: bool },
+    CardsTaken { player_index: usize, cards: Vec<cards::Card>, source: CardsSource },
+    TrumpCardDrawn { player_index: usize, trump_card: cards::Card },
+    PlayerFinished { player_index: usize },
+    GameOver,
+}
+
+pub type Events = Vec<Event>;
+
+impl std::fmt::Display for Action
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
+    {
+        match self
+        {
+            Self::Attack(card) => write!(f, "attack with the {card}"),
+            Self::ThrowIn(card) => write!(f, "throw in the {card}"),
+            Self::Defend(attack_card_index, card) => write!(f, "beat attack card #{attack_card_index} with the {card}"),
+            Self::Transfer(card) => write!(f, "transfer with the {card}"),
+            Self::ShowTrump(card) => write!(f, "transfer showing the {card}"),
+            Self::Pass => write!(f, "pass"),
+            Self::Take => write!(f, "take"),
+            Self::Accuse(card) => write!(f, "accuse the {card}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase
+{
+    /// First attacker starts the bout
+    Attack,
+    /// Defender beats, takes or transfers the attack
+    Defense,
+    /// Current attacker throws in or passes
+    ThrowIn,
+    /// Players in turn can accuse a card of being played illegally
+    Challenge(usize),
+    GameOver,
+}
+
+/// Public part of the round which is enough to continue the game from the middle of it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RoundPosition
+{
+    pub first_attacking_index : usize,
+    pub attacking_index       : usize,
+    pub defending_index       : usize,
+    pub is_defender_taking    : bool,
+    pub is_trump_shown        : bool,
+}
+
+/// Everything which matters for the rest of the game, the discard pile and the stock are defined by the other cards
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PositionKey
+{
+    trump              : cards::Suit,
+    hands              : Vec<Vec<cards::Card>>,
+    played_cards       : Vec<(cards::Card, Option<cards::Card>)>,
+    remain_cards_count : usize,
+    active_players     : Vec<usize>,
+    phase              : Phase,
+    round              : (bool, usize, usize, usize, usize, bool),
+}
+
+#[derive(Clone, Default)]
+struct RoundInfo
+{
+    is_defense_succeed: bool,
+    first_attacking_index: usize,
+    attacking_index: usize,
+    defending_index: usize,
+    passes_count: usize,
+    is_trump_shown: bool,
+    /// Illegally played cards and their players
+    cheats: Vec<(usize, cards::Card)>,
+    /// Players asked to challenge in order
+    accusers: Vec<usize>,
+}
+
+/// Rules engine: whole game state which changes only by applying actions of the current player
+#[derive(Clone)]
+pub struct GameState
+{
+    settings        : Settings,
+    table           : Table,
+    hands           : Vec<Vec<cards::Card>>,
+    active_players  : Vec<usize>,
+    finishing_order : Vec<usize>,
+    rounds_count    : usize,
+    phase           : Phase,
+    round_info      : RoundInfo,
+}
+
+impl GameState
+{
+    /// Remaining cards may go around forever when nobody can beat them
+    pub const MAX_ROUNDS_COUNT: usize = 1000;
+    pub const CHEAT_PENALTY_CARDS_COUNT: usize = 2;
+
+    pub fn new(settings: & Settings) -> Self
+    {
+        Self
+        {
+            settings        : *settings,
+            table           : Table::new(settings),
+            hands           : vec![],
+            active_players  : vec![],
+            finishing_order : vec![],
+            rounds_count    : 0,
+            phase           : Phase::GameOver,
+            round_info      : Default::default(),
+        }
+    }
+
+    /// Game continued from the middle of the round, e.g. with guessed hands of opponents to simulate the rest of it.
+    /// Players who aren't in the finishing order are active
+    pub fn from_position(settings: & Settings, table: Table, hands: Vec<Vec<cards::Card>>, finishing_order: Vec<usize>, phase: Phase, round: RoundPosition) -> Self
+    {
+        let active_players = (0 .. hands.len()).filter(|player_index| !finishing_order.contains(player_index)).collect();
+        Self
+        {
+            settings        : *settings,
+            table,
+            hands,
+            active_players,
+            finishing_order,
+            rounds_count    : 0,
+            phase,
+            round_info      : RoundInfo
+            {
+                is_defense_succeed    : !round.is_defender_taking,
+                first_attacking_index : round.first_attacking_index,
+                attacking_index       : round.attacking_index,
+                defending_index       : round.defending_index,
+                is_trump_shown        : round.is_trump_shown,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Shuffles the deck, serves cards, chooses the first attacker and starts the first round
+    pub fn deal(&mut self, players_count: usize, previous_durak_index: Option<usize>, rng: &mut GameRng) -> Events
+    {
+        let mut events = vec![];
+        self.table.reset(rng);
+        self.hands = vec![Vec::with_capacity(cards::CARDS_IN_DECK_COUNT); players_count];
+        self.active_players = (0 .. players_count).collect();
+        self.finishing_order.clear();
+        self.rounds_count = 0;
+        self.round_info = Default::default();
+
+        for player_index in 0 .. players_count
+        {
+            self.draw_stock_cards(player_index, cards::CARDS_IN_DECK_COUNT, &mut events);
+        }
+
+        let (first_attacking_index, reason) = match (self.settings.starting_rule(), previous_durak_index)
+        {
+            (StartingRule::LowestTrump, Some(durak_index)) => ((durak_index + 1) % players_count, StartReason::AfterDurak(durak_index)),
+            (StartingRule::LowestTrump, None) =>
+                match self.lowest_trump_holder()
+                {
+                    Some((player_index, trump)) => (player_index, StartReason::LowestTrump(trump)),
+                    None => (rng.gen_range(0 .. players_count), StartReason::NoTrumps),
+                },
+            (StartingRule::Random, _) => (rng.gen_range(0 .. players_count), StartReason::Random),
+        };
+        events.push(Event::FirstAttackerChosen {player_index: first_attacking_index, reason});
+
+        self.round_info.first_attacking_index = first_attacking_index;
+        self.start_round(&mut events);
+        events
+    }
+
+    // --- getters ---
+
+    pub fn table(& self) -> & Table
+    {
+        & self.table
+    }
+
+    /// # For test only!
+    pub fn table_mut(&mut self) -> &mut Table
+    {
+        &mut self.table
+    }
+
+    pub fn hand(& self, player_index: usize) -> & Vec<cards::Card>
+    {
+        & self.hands[player_index]
+    }
+
+    pub fn players_count(& self) -> usize
+    {
+        self.hands.len()
+    }
+
+    pub fn phase(& self) -> Phase
+    {
+        self.phase
+    }
+
+    pub fn rounds_count(& self) -> usize
+    {
+        self.rounds_count
+    }
+
+    /// Indices of players who haven't got rid of their cards yet
+    pub fn active_players(& self) -> & Vec<usize>
+    {
+        & self.active_players
+    }
+
+    pub fn finishing_order(& self) -> & Vec<usize>
+    {
+        & self.finishing_order
+    }
+
+    pub fn first_attacking_index(& self) -> usize
+    {
+        self.round_info.first_attacking_index
+    }
+
+    pub fn attacking_index(& self) -> usize
+    {
+        self.round_info.attacking_index
+    }
+
+    pub fn defending_index(& self) -> usize
+    {
+        self.round_info.defending_index
+    }
+
+    /// Player who has to make the next action, `None` when the game is over
+    pub fn current_player(& self) -> Option<usize>
+    {
+        match self.phase
+        {
+            Phase::Attack | Phase::ThrowIn => Some(self.round_info.attacking_index),
+            Phase::Defense => Some(self.round_info.defending_index),
+            Phase::Challenge(accuser_number) => Some(self.round_info.accusers[accuser_number]),
+            Phase::GameOver => None,
+        }
+    }
+
+    pub fn is_game_over(& self) -> bool
+    {
+        self.phase == Phase::GameOver
+    }
+
+    pub fn is_stalemate(& self) -> bool
+    {
+        self.rounds_count >= Self::MAX_ROUNDS_COUNT
+    }
+
+    /// The only player left with cards
+    pub fn durak(& self) -> Option<usize>
+    {
+        if self.active_players.len() == 1 {Some(self.active_players[0])} else {None}
+    }
+
+    /// Last players got rid of their cards simultaneously or the game got stuck
+    pub fn is_draw(& self) -> bool
+    {
+        self.active_players.is_empty() || (self.active_players.len() > 1 && self.losing_team().is_none() && self.is_stalemate())
+    }
+
+    /// Only when the game is over: the player is the durak, in the losing team or didn't win the game played until the first win
+    pub fn has_lost(& self, player_index: usize) -> bool
+    {
+        if self.is_draw()
+        {
+            return false;
+        }
+        match (self.durak(), self.losing_team())
+        {
+            (_, Some(team)) => self.team(player_index) == Some(team),
+            (Some(durak_index), _) => durak_index == player_index,
+            (None, None) => !self.finishing_order.contains(& player_index),
+        }
+    }
+
+    /// Two states with the same key go on the same way
+    pub fn position_key(& self) -> PositionKey
+    {
+        let round_info = & self.round_info;
+        PositionKey
+        {
+            trump              : self.table.trump(),
+            hands              : self.hands.clone(),
+            played_cards       : self.table.played_cards().clone(),
+            remain_cards_count : self.table.remain_cards_count(),
+            active_players     : self.active_players.clone(),
+            phase              : self.phase,
+            round              : (round_info.is_defense_succeed, round_info.first_attacking_index, round_info.attacking_index, round_info.defending_index,
+                round_info.passes_count, round_info.is_trump_shown),
+        }
+    }
+
+    /// Only in team play: partners sit opposite each other, so the team is defined by the seat
+    pub fn team(& self, player_index: usize) -> Option<usize>
+    {
+        if self.settings.team_play() {Some(player_index % Settings::TEAMS_COUNT)} else {None}
+    }
+
+    /// Only in team play: the team of players left with cards
+    pub fn losing_team(& self) -> Option<usize>
+    {
+        let team = self.team(*self.active_players.first()?)?;
+        if self.active_players.iter().all(|player_index| self.team(*player_index) == Some(team)) {Some(team)} else {None}
+    }
+
+    /// Returns the next active player clockwise who isn't a partner of the given player
+    pub fn next_opponent_index(& self, player_index: usize) -> usize
+    {
+        (1 ..= self.players_count())
+            .map(|offset| (player_index + offset) % self.players_count())
+            .find(|next_index| self.active_players.contains(next_index) && (self.team(*next_index) != self.team(player_index) || self.team(player_index).is_none()))
+            .unwrap_or(player_index)
+    }
+
+    /// Returns the next active player clockwise, given player may be already inactive
+    pub fn next_active_index(& self, player_index: usize) -> usize
+    {
+        (1 ..= self.players_count())
+            .map(|offset| (player_index + offset) % self.players_count())
+            .find(|next_index| self.active_players.contains(next_index))
+            .unwrap_or(player_index)
+    }
+
+    /// Returns index of the player with the lowest trump (jokers aren't trumps) and the trump itself
+    pub fn lowest_trump_holder(& self) -> Option<(usize, cards::Card)>
+    {
+        let trump = self.table.trump();
+        self.hands.iter().enumerate()
+            .filter_map(|(player_index, hand)|
+                hand.iter()
+                    .filter(|card| card.suit() == trump && !card.is_joker())
+                    .min()
+                    .map(|card| (player_index, *card)))
+            .min_by_key(|(_, card)| card.value())
+    }
+
+    pub fn next_defending_index(& self) -> usize
+    {
+        self.next_opponent_index(self.round_info.defending_index)
+    }
+
+    /// Defender announced taking the cards, but attackers still can throw in
+    pub fn is_defender_taking(& self) -> bool
+    {
+        self.phase == Phase::ThrowIn && !self.round_info.is_defense_succeed
+    }
+
+    /// Only for perevodnoy: defender can transfer before the defense has started
+    pub fn can_transfer(& self) -> bool
+    {
+        self.phase == Phase::Defense
+            && self.settings.variant() == Variant::Perevodnoy
+            && self.table.is_defense_not_started()
+    }
+
+    pub fn can_show_trump(& self) -> bool
+    {
+        self.can_transfer() && self.settings.trump_show_transfer() && !self.round_info.is_trump_shown
+    }
+
+    /// Active players except the defender who still have cards to throw in
+    fn attackers_count(& self) -> usize
+    {
+        self.active_players.iter()
+            .filter(|player_index| self.can_throw_in(**player_index))
+            .count()
+    }
+
+    /// Returns the previous active player clockwise, given player may be already inactive
+    pub fn previous_active_index(& self, player_index: usize) -> usize
+    {
+        (1 ..= self.players_count())
+            .map(|offset| (player_index + self.players_count() - offset) % self.players_count())
+            .find(|previous_index| self.active_players.contains(previous_index))
+            .unwrap_or(player_index)
+    }
+
+    /// Whether the active player is allowed to throw in cards to the current defender
+    pub fn can_throw_in(& self, player_index: usize) -> bool
+    {
+        // partners never attack each other
+        let defending_index = self.round_info.defending_index;
+        if player_index == defending_index || self.hands[player_index].is_empty()
+            || self.team(player_index).is_some_and(|team| self.team(defending_index) == Some(team))
+        {
+            return false;
+        }
+
+        // neighbors are counted among the defender's opponents, partners and finished players are skipped
+        let is_left_neighbor = self.next_opponent_index(player_index) == defending_index;
+        match self.settings.throw_in_rule()
+        {
+            ThrowInRule::Everybody => true,
+            ThrowInRule::Neighbors => is_left_neighbor || player_index == self.next_opponent_index(defending_index),
+            ThrowInRule::LeftNeighbor => is_left_neighbor,
+        }
+    }
+
+    // --- actions ---
+
+    /// All legal actions of the current player, cheating moves aren't included
+    pub fn legal_actions(& self) -> Vec<Action>
+    {
+        let player_index = match self.current_player()
+        {
+            Some(player_index) => player_index,
+            None => return vec![],
+        };
+        let hand = & self.hands[player_index];
+        let defender_cards_count = self.hands[self.round_info.defending_index].len();
+
+        match self.phase
+        {
+            Phase::Attack => self.table.legal_attacks(hand, defender_cards_count).into_iter()
+                .map(Action::Attack)
+                .collect(),
+            Phase::ThrowIn =>
+            {
+                let mut actions: Vec<Action> = self.table.legal_attacks(hand, defender_cards_count).into_iter()
+                    .map(Action::ThrowIn)
+                    .collect();
+                actions.push(Action::Pass);
+                actions
+            },
+            Phase::Defense =>
+            {
+                let mut actions = vec![];
+                if self.can_transfer()
+                {
+                    let next_defender_cards_count = self.hands[self.next_defending_index()].len();
+                    for card in hand.iter()
+                    {
+                        if self.table.check_transfer(& Transfer::Card(*card), next_defender_cards_count).is_ok()
+                        {
+                            actions.push(Action::Transfer(*card));
+                        }
+                        if self.can_show_trump() && self.table.check_transfer(& Transfer::ShowTrump(*card), next_defender_cards_count).is_ok()
+                        {
+                            actions.push(Action::ShowTrump(*card));
+                        }
+                    }
+                }
+
+                actions.extend(self.table.legal_defenses(hand).into_iter()
+                    .map(|(attack_card_index, card)| Action::Defend(attack_card_index, card)));
+                actions.push(Action::Take);
+                actions
+            },
+            Phase::Challenge(_) =>
+            {
+                let mut actions: Vec<Action> = self.table.attack_cards().into_iter()
+                    .chain(self.table.defense_cards())
+                    .map(Action::Accuse)
+                    .collect();
+                actions.push(Action::Pass);
+                actions
+            },
+            Phase::GameOver => vec![],
+        }
+    }
+
+    /// Applies actions one after another, the state isn't changed if any of them is illegal
+    pub fn apply_all(&mut self, actions: & [Action]) -> Result<Events, Error>
+    {
+        if let [action] = actions
+        {
+            return self.apply(*action);
+        }
+
+        let mut state = self.clone();
+        let mut events = vec![];
+        for action in actions
+        {
+            events.extend(state.apply(*action)?);
+        }
+        *self = state;
+        Ok(events)
+    }
+
+    /// Action which is applied when the current player forfeits his/her move
+    pub fn forfeit_action(& self) -> Option<Action>
+    {
+        let player_index = self.current_player()?;
+        Some(match self.phase
+        {
+            Phase::Attack => Action::Attack(self.hands[player_index][0]),
+            Phase::Defense => Action::Take,
+            _ => Action::Pass,
+        })
+    }
+
+    /// Applies the action of the current player, the state isn't changed if the action is illegal
+    pub fn apply(&mut self, action: Action) -> Result<Events, Error>
+    {
+        let player_index = self.current_player().ok_or(Error::UnexpectedAction)?;
+        let mut events = vec![];
+
+        match (self.phase, action)
+        {
+            (Phase::Attack, Action::Attack(card)) =>
+            {
+                self.check_card_in_hand(player_index, & card)?;
+                self.table.check_attack_card(& card, true, self.hands[self.round_info.defending_index].len())?;
+                self.play_attack_card(player_index, card, &mut events);
+                events.push(Event::Attacked {player_index, card});
+                self.wait_for_defense(&mut events);
+            },
+            (Phase::ThrowIn, Action::ThrowIn(card)) =>
+            {
+                self.check_card_in_hand(player_index, & card)?;
+                match self.table.check_attack_card(& card, false, self.hands[self.round_info.defending_index].len())
+                {
+                    Ok(()) => (),
+                    Err(Error::AbsentCardValue(_)) if self.settings.cheats_allowed() => self.round_info.cheats.push((player_index, card)),
+                    Err(error) => return Err(error),
+                }
+                self.play_attack_card(player_index, card, &mut events);
+                events.push(Event::ThrewIn {player_index, card});
+                self.wait_for_defense(&mut events);
+            },
+            (Phase::ThrowIn, Action::Pass) =>
+            {
+                events.push(Event::Passed {player_index});
+
+                self.round_info.passes_count += 1;
+                self.round_info.attacking_index = self.next_active_index(self.round_info.attacking_index);
+                self.continue_attack(&mut events);
+            },
+            (Phase::Defense, Action::Transfer(card)) | (Phase::Defense, Action::ShowTrump(card)) =>
+            {
+                self.check_card_in_hand(player_index, & card)?;
+                if !self.can_transfer()
+                {
+                    return Err(Error::UnexpectedAction);
+                }
+
+                let next_defending_index = self.next_defending_index();
+                let next_defender_cards_count = self.hands[next_defending_index].len();
+                if let Action::ShowTrump(_) = action
+                {
+                    if !self.can_show_trump()
+                    {
+                        return Err(Error::InvalidTrumpShow);
+                    }
+                    self.table.check_transfer(& Transfer::ShowTrump(card), next_defender_cards_count)?;
+                    self.round_info.is_trump_shown = true;
+                    events.push(Event::TrumpShown {player_index, card, defending_index: next_defending_index});
+                }
+                else
+                {
+                    self.table.check_transfer(& Transfer::Card(card), next_defender_cards_count)?;
+                    self.play_attack_card(player_index, card, &mut events);
+                    events.push(Event::Transferred {player_index, card, defending_index: next_defending_index});
+                }
+                self.round_info.defending_index = next_defending_index;
+            },
+            (Phase::Defense, Action::Defend(attack_card_index, card)) =>
+            {
+                self.check_card_in_hand(player_index, & card)?;
+                match self.table.check_defense_card(& card, attack_card_index)
+                {
+                    Ok(()) => (),
+                    Err(Error::IncorrectDefense) if self.settings.cheats_allowed() => self.round_info.cheats.push((player_index, card)),
+                    Err(error) => return Err(error),
+                }
+                self.remove_card(player_index, & card);
+                events.push(Event::Defended {player_index, attack_card: self.table.played_cards()[attack_card_index].0, defense_card: card});
+                self.table.take_defense_card(card, attack_card_index);
+
+                // every attack card has to be beaten before attackers continue
+                if self.table.is_attack_beaten()
+                {
+                    self.round_info.passes_count = 0;
+                    self.continue_attack(&mut events);
+                }
+            },
+            (Phase::Defense, Action::Take) =>
+            {
+                // attackers still can throw in cards for the defender to take
+                events.push(Event::Took {player_index});
+                self.round_info.is_defense_succeed = false;
+                self.round_info.passes_count = 0;
+                self.continue_attack(&mut events);
+            },
+            (Phase::Challenge(accuser_number), Action::Accuse(card)) =>
+            {
+                if !self.table.played_cards().iter().any(|(attack_card, defense_card)| *attack_card == card || *defense_card == Some(card))
+                {
+                    return Err(Error::AbsentCard(card));
+                }
+
+                let cheat = self.round_info.cheats.iter()
+                    .find(|(cheater_index, cheat_card)| *cheat_card == card && *cheater_index != player_index)
+                    .copied();
+                match cheat
+                {
+                    Some((cheater_index, _)) =>
+                    {
+                        // caught cheater takes all cards from the table, defense fails only if the defender cheated
+                        events.push(Event::CheatCaught {accuser_index: player_index, cheater_index, card});
+                        self.take_played_cards(cheater_index, &mut events);
+                        self.round_info.is_defense_succeed = cheater_index != self.round_info.defending_index;
+                        self.resolve_round(&mut events);
+                    },
+                    None =>
+                    {
+                        events.push(Event::FalseAccusation {accuser_index: player_index, card});
+                        self.take_penalty_cards(player_index, &mut events);
+                        self.continue_challenge(accuser_number + 1, &mut events);
+                    },
+                }
+            },
+            (Phase::Challenge(accuser_number), Action::Pass) => self.continue_challenge(accuser_number + 1, &mut events),
+            _ => return Err(Error::UnexpectedAction),
+        }
+        Ok(events)
+    }
+
+    // --- round flow ---
+
+    fn start_round(&mut self, events: &mut Events)
+    {
+        self.rounds_count += 1;
+        let first_attacking_index = self.round_info.first_attacking_index;
+        self.round_info = RoundInfo
+        {
+            is_defense_succeed: true,
+            first_attacking_index,
+            attacking_index: first_attacking_index,
+            defending_index: self.next_opponent_index(first_attacking_index),
+            ..Default::default()
+        };
+        self.phase = Phase::Attack;
+        events.push(Event::RoundStarted {attacking_index: first_attacking_index, defending_index: self.round_info.defending_index});
+    }
+
+    /// Defender responds to every attack card at once or right after it was played
+    fn wait_for_defense(&mut self, events: &mut Events)
+    {
+        self.round_info.passes_count = 0;
+        if self.settings.defense_after_throw_ins() || !self.round_info.is_defense_succeed
+        {
+            self.continue_attack(events);
+        }
+        else
+        {
+            self.phase = Phase::Defense;
+        }
+    }
+
+    /// Finds the next attacker to throw in, otherwise the defender responds or the bout ends
+    fn continue_attack(&mut self, events: &mut Events)
+    {
+        loop
+        {
+            // all attackers passed one after another or defender could beat the attack with his/her last card
+            let defender_cards_count = self.hands[self.round_info.defending_index].len();
+            if self.round_info.passes_count >= self.attackers_count()
+                || defender_cards_count == 0
+                || self.table.is_attack_finished(defender_cards_count)
+            {
+                if self.table.is_attack_beaten() || !self.round_info.is_defense_succeed
+                {
+                    self.end_bout(events);
+                }
+                else
+                {
+                    self.phase = Phase::Defense;
+                }
+                return;
+            }
+
+            // players without cards and players who aren't allowed to throw in are skipped without passing
+            if !self.can_throw_in(self.round_info.attacking_index)
+            {
+                self.round_info.attacking_index = self.next_active_index(self.round_info.attacking_index);
+                continue;
+            }
+
+            self.phase = Phase::ThrowIn;
+            return;
+        }
+    }
+
+    fn end_bout(&mut self, events: &mut Events)
+    {
+        if self.settings.cheats_allowed()
+        {
+            let first_attacking_index = self.round_info.first_attacking_index;
+            self.round_info.accusers = (0 .. self.players_count())
+                .map(|i| (first_attacking_index + i) % self.players_count())
+                .filter(|player_index| self.active_players.contains(player_index))
+                .collect();
+            self.continue_challenge(0, events);
+        }
+        else
+        {
+            self.resolve_round(events);
+        }
+    }
+
+    fn continue_challenge(&mut self, accuser_number: usize, events: &mut Events)
+    {
+        if accuser_number < self.round_info.accusers.len() && !self.table.played_cards().is_empty()
+        {
+            self.phase = Phase::Challenge(accuser_number);
+        }
+        else
+        {
+            self.resolve_round(events);
+        }
+    }
+
+    /// Players draw cards, finished players leave the game and the next round starts
+    fn resolve_round(&mut self, events: &mut Events)
+    {
+        let defending_index = self.round_info.defending_index;
+        let first_attacking_index = self.round_info.first_attacking_index;
+        events.push(Event::BoutEnded {defending_index, is_defense_succeed: self.round_info.is_defense_succeed});
+
+        // attacing players draw cards
+        for i in 0 .. self.players_count()
+        {
+            let player_index = (first_attacking_index + i) % self.players_count();
+            if player_index != defending_index
+            {
+                self.draw_missing_cards(player_index, events);
+            }
+        }
+
+        // defending player draws cards
+        if self.round_info.is_defense_succeed
+        {
+            self.table.discard_cards();
+            self.draw_missing_cards(defending_index, events);
+        }
+        else
+        {
+            self.take_played_cards(defending_index, events);
+        }
+
+        for i in 0 .. self.players_count()
+        {
+            self.check_player_finished((first_attacking_index + i) % self.players_count(), events);
+        }
+
+        // team game goes on until only partners are left
+        if self.active_players.len() <= 1
+            || (self.settings.finish_after_first_win() && !self.settings.team_play() && !self.finishing_order.is_empty())
+            || self.losing_team().is_some()
+            || self.is_stalemate()
+        {
+            self.phase = Phase::GameOver;
+            events.push(Event::GameOver);
+            return;
+        }
+
+        // choose next player
+        self.round_info.first_attacking_index =
+            if self.round_info.is_defense_succeed && self.active_players.contains(& defending_index)
+            {
+                defending_index
+            }
+            else
+            {
+                self.next_active_index(defending_index)
+            };
+        self.start_round(events);
+    }
+
+    // --- cards movement ---
+
+    fn check_card_in_hand(& self, player_index: usize, card: & cards::Card) -> Result<(), Error>
+    {
+        if self.hands[player_index].contains(card) {Ok(())} else {Err(Error::AbsentCard(*card))}
+    }
+
+    fn remove_card(&mut self, player_index: usize, card: & cards::Card)
+    {
+        self.hands[player_index].retain(|hand_card| hand_card != card);
+    }
+
+    fn play_attack_card(&mut self, player_index: usize, card: cards::Card, events: &mut Events)
+    {
+        self.remove_card(player_index, & card);
+        self.table.take_attack_card(card);
+
+        // caught cheater can get cards back until the end of the round
+        if !self.settings.cheats_allowed()
+        {
+            self.check_player_finished(player_index, events);
+        }
+    }
+
+    /// Player finishes the game when he/she has no cards and can't draw them from the stock anymore
+    fn check_player_finished(&mut self, player_index: usize, events: &mut Events)
+    {
+        if !self.hands[player_index].is_empty()
+            || self.table.remain_cards_count() > 0
+            || self.finishing_order.contains(& player_index)
+        {
+            return;
+        }
+
+        self.active_players.retain(|active_index| *active_index != player_index);
+        self.finishing_order.push(player_index);
+        events.push(Event::PlayerFinished {player_index});
+    }
+
+    fn take_cards(&mut self, player_index: usize, cards: Vec<cards::Card>, source: CardsSource, events: &mut Events)
+    {
+        if cards.is_empty()
+        {
+            return;
+        }
+        self.hands[player_index].extend(cards.iter().copied());
+        self.hands[player_index].sort();
+        events.push(Event::CardsTaken {player_index, cards, source});
+    }
+
+    fn draw_missing_cards(&mut self, player_index: usize, events: &mut Events)
+    {
+        let missing_cards_count = positive_sub_or_zero(cards::CARDS_IN_DECK_COUNT, self.hands[player_index].len());
+        self.draw_stock_cards(player_index, missing_cards_count, events);
+    }
+
+    fn draw_stock_cards(&mut self, player_index: usize, count: usize, events: &mut Events)
+    {
+        let cards: Vec<cards::Card> = match self.table.draw_stock_cards(count)
+        {
+            Some(cards) => cards.collect(),
+            None => return,
+        };
+        self.take_cards(player_index, cards, CardsSource::Stock, events);
+
+        if self.table.is_trump_card_drawn()
+        {
+            events.push(Event::TrumpCardDrawn {player_index, trump_card: self.table.trump_card()});
+        }
+    }
+
+    fn take_played_cards(&mut self, player_index: usize, events: &mut Events)
+    {
+        let cards: Vec<cards::Card> = match self.table.draw_played_cards()
+        {
+            Ok(cards) => cards.collect(),
+            Err(_) => return,
+        };
+        self.take_cards(player_index, cards, CardsSource::Table, events);
+    }
+
+    fn take_penalty_cards(&mut self, player_index: usize, events: &mut Events)
+    {
+        if self.table.remain_cards_count() > 0
+        {
+            self.draw_stock_cards(player_index, Self::CHEAT_PENALTY_CARDS_COUNT, events);
+        }
+        else
+        {
+            let cards = self.table.draw_discarded_cards(Self::CHEAT_PENALTY_CARDS_COUNT).collect();
+            self.take_cards(player_index, cards, CardsSource::Discard, events);
+        }
+    }
+}
@@ -4,64 +4,95 @@ use rand::Rng;
 use rand::seq::SliceRandom;
 
 use crate::cards;
+use crate::game::Settings;
 use crate::utils::*;
 
+/// Way the defender passes the attack on to the next player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer
+{
+    /// Card of the attack value is laid down as another attack card
+    Card(cards::Card),
+    /// Trump of the attack value is only shown and stays in hand
+    ShowTrump(cards::Card),
+}
+
+#[derive(Clone)]
 pub struct Table
 {
-    attack_cards    : Vec<cards::Card>,
-    defense_cards   : Vec<cards::Card>,
-    discarded_cards : Vec<cards::Card>,
-    card_stock      : Vec<cards::Card>,
-    trump           : cards::Suit,
+    /// Attack cards paired with cards which beat them
+    played_cards        : Vec<(cards::Card, Option<cards::Card>)>,
+    discarded_cards     : Vec<cards::Card>,
+    card_stock          : Vec<cards::Card>,
+    trump_card          : cards::Card,
+    card_deck           : cards::Deck,
+    joker_rule          : cards::JokerRule,
+    is_first_bout       : bool,
+    first_bout_limit    : bool,
+    defender_cards_limit: bool,
+    cheats_allowed      : bool,
 }
 
 impl Table
 {
-    pub fn new(card_deck: cards::Deck) -> Self
+    pub fn new(settings: & Settings) -> Self
     {
-        let card_deck = card_deck as usize;
+        let card_deck = settings.card_deck();
         Self
         {
-            attack_cards    : Vec::with_capacity(6),
-            defense_cards   : Vec::with_capacity(6),
-            discarded_cards : Vec::with_capacity(card_deck),
-            card_stock      : Vec::with_capacity(card_deck),
-            trump           : cards::Suit::Spade,
+            played_cards        : Vec::with_capacity(6),
+            discarded_cards     : Vec::with_capacity(card_deck as usize),
+            card_stock          : Vec::with_capacity(card_deck as usize),
+            trump_card          : cards::Card::new(cards::Value::Ace, cards::Suit::Spade),
+            card_deck,
+            joker_rule          : settings.joker_rule(),
+            is_first_bout       : true,
+            first_bout_limit    : settings.first_bout_limit(),
+            defender_cards_limit: settings.defender_cards_limit(),
+            cheats_allowed      : settings.cheats_allowed(),
         }
     }
 
-    pub fn reset(&mut self)
+    pub fn reset(&mut self, rng: &mut GameRng)
     {
         self.discarded_cards.clear();
-        self.attack_cards.clear();
-        self.defense_cards.clear();
-        self.card_stock.clear();
+        self.played_cards.clear();
+        self.is_first_bout = true;
 
-        for i in 2..=14
+        self.card_stock = self.card_deck.cards();
+        debug_assert_eq!(self.card_stock.len(), self.card_deck as usize);
+
+        self.card_stock.shuffle(rng);
+
+        // trump card lies at the bottom of the stock and is drawn last, joker can't define trump
+        if let Some(index) = self.card_stock.iter().position(|card| !card.is_joker())
         {
-            let card_value = cards::Value::from_usize(i);
-            self.card_stock.push(cards::Card::new(card_value, cards::Suit::Club));
-            self.card_stock.push(cards::Card::new(card_value, cards::Suit::Spade));
-            self.card_stock.push(cards::Card::new(card_value, cards::Suit::Heart));
-            self.card_stock.push(cards::Card::new(card_value, cards::Suit::Diamond));
+            self.card_stock.swap(0, index);
         }
-        let mut rng = rand::thread_rng();
-        self.card_stock.shuffle(&mut rng);
+        self.trump_card = self.card_stock[0];
+    }
 
-        self.trump = self.card_stock[0].suit();
+    // --- getters ---
 
+    pub fn played_cards(& self) -> & Vec<(cards::Card, Option<cards::Card>)>
+    {
+        & self.played_cards
     }
 
-    // --- getters ---
+    pub fn attack_cards(& self) -> Vec<cards::Card>
+    {
+        self.played_cards.iter().map(|(attack_card, _)| *attack_card).collect()
+    }
 
-    pub fn attack_cards(& self) -> & Vec<cards::Card>
+    /// Only played defense cards in order of attack cards they beat
+    pub fn defense_cards(& self) -> Vec<cards::Card>
     {
-        & self.attack_cards
+        self.played_cards.iter().filter_map(|(_, defense_card)| *defense_card).collect()
     }
 
-    pub fn defense_cards(& self) -> & Vec<cards::Card>
+    pub fn card_deck(& self) -> cards::Deck
     {
-        & self.defense_cards
+        self.card_deck
     }
 
     pub fn remain_cards_count(& self) -> usize
@@ -69,33 +100,94 @@ impl Table
         self.card_stock.len()
     }
 
+    pub fn discarded_cards_count(& self) -> usize
+    {
+        self.discarded_cards.len()
+    }
+
     pub fn trump(& self) -> cards::Suit
     {
-        self.trump
+        self.trump_card.suit()
+    }
+
+    /// Trump card is public even after it was drawn from the stock
+    pub fn trump_card(& self) -> cards::Card
+    {
+        self.trump_card
+    }
+
+    pub fn is_trump_card_drawn(& self) -> bool
+    {
+        self.card_stock.is_empty()
+    }
+
+    pub fn joker_rule(& self) -> cards::JokerRule
+    {
+        self.joker_rule
     }
 
     // --- consume player cards ---
 
-    pub fn is_attack_finished(& self) -> bool
+    pub fn cheats_allowed(& self) -> bool
+    {
+        self.cheats_allowed
+    }
+
+    pub fn is_first_bout(& self) -> bool
+    {
+        self.is_first_bout
+    }
+
+    pub fn max_attack_cards_count(& self) -> usize
+    {
+        if self.first_bout_limit && self.is_first_bout
+        {
+            cards::FIRST_BOUT_CARDS_COUNT
+        }
+        else
+        {
+            cards::CARDS_IN_DECK_COUNT
+        }
+    }
+
+    pub fn unbeaten_cards_count(& self) -> usize
+    {
+        self.played_cards.iter().filter(|(_, defense_card)| defense_card.is_none()).count()
+    }
+
+    pub fn is_attack_finished(& self, defender_cards_count: usize) -> bool
     {
-        self.attack_cards.len() >= cards::CARDS_IN_DECK_COUNT
+        self.check_attack_limits(defender_cards_count).is_err()
     }
 
-    pub fn check_attack_card(& self, attack_card: & cards::Card, is_first_attack: bool) -> Result<(), Error>
+    fn check_attack_limits(& self, defender_cards_count: usize) -> Result<(), Error>
     {
-        if self.is_attack_finished()
+        if self.played_cards.len() >= self.max_attack_cards_count()
+        {
+            Err(Error::TooManyAttackCards(self.max_attack_cards_count()))
+        }
+        else if self.defender_cards_limit && self.unbeaten_cards_count() >= defender_cards_count
         {
-            return Err(Error::TooManyAttackCards);
+            Err(Error::NotEnoughDefenderCards(defender_cards_count))
+        }
+        else
+        {
+            Ok(())
         }
+    }
+
+    pub fn check_attack_card(& self, attack_card: & cards::Card, is_first_attack: bool, defender_cards_count: usize) -> Result<(), Error>
+    {
+        self.check_attack_limits(defender_cards_count)?;
 
         if is_first_attack
         {
             return Ok(());
         }
  
-        for played_card in self.defense_cards.iter().chain(self.attack_cards.iter())
+        for (played_attack_card, defense_card) in self.played_cards.iter()
         {
-            if attack_card.value() == played_card.value()
+            if attack_card.value() == played_attack_card.value() || defense_card.is_some_and(|card| attack_card.value() == card.value())
             {
                 return Ok(());
             }
@@ -104,25 +196,48 @@ impl Table
        Err(Error::AbsentCardValue(attack_card.value()))
     }
 
+    /// Cards from the hand which can be thrown in now, the attack is started when the table is empty
+    pub fn legal_attacks(& self, hand: & [cards::Card], defender_cards_count: usize) -> Vec<cards::Card>
+    {
+        let is_first_attack = self.played_cards.is_empty();
+        hand.iter()
+            .filter(|card| self.check_attack_card(card, is_first_attack, defender_cards_count).is_ok())
+            .copied()
+            .collect()
+    }
+
     pub fn take_attack_card(&mut self, attack_card: cards::Card)
     {
-        self.attack_cards.push(attack_card);
+        self.played_cards.push((attack_card, None));
     }
 
     pub fn is_attack_beaten(& self) -> bool
     {
-        self.attack_cards.len() == self.defense_cards.len()
+        self.played_cards.iter().all(|(_, defense_card)| defense_card.is_some())
     }
 
     pub fn can_beat(& self, defense_card: & cards::Card, attack_card_index: usize) -> bool
     {
-        let attack_card = & self.attack_cards[attack_card_index];
-        if defense_card.suit() != self.trump
+        self.beats(defense_card, & self.played_cards[attack_card_index].0)
+    }
+
+    /// Whether the defense card beats the attack card with the current trump and joker rule
+    pub fn beats(& self, defense_card: & cards::Card, attack_card: & cards::Card) -> bool
+    {
+        if attack_card.is_joker()
+        {
+            defense_card.is_joker()
+        }
+        else if defense_card.is_joker()
+        {
+            defense_card.joker_beats(attack_card, self.joker_rule)
+        }
+        else if defense_card.suit() != self.trump()
         {
             defense_card.suit() == attack_card.suit() &&
             defense_card.value() > attack_card.value()
         }
-        else if attack_card.suit() != self.trump
+        else if attack_card.suit() != self.trump()
         {
             true
         }
@@ -138,11 +253,15 @@ impl Table
         {
             Err(Error::NoCardsToBeat)
         }
-        else if attack_card_index >= self.attack_cards.len()
+        else if attack_card_index >= self.played_cards.len()
         {
             Err(Error::InvalidAttackIndex(attack_card_index))
         }
-        else if !self.can_beat(& defense_card, attack_card_index)
+        else if self.played_cards[attack_card_index].1.is_some()
+        {
+            Err(Error::AttackCardBeaten(attack_card_index))
+        }
+        else if !self.can_beat(defense_card, attack_card_index)
         {
             Err(Error::IncorrectDefense)
         }
@@ -152,17 +271,133 @@ impl Table
         }
     }
 
+    /// Pairs of unbeaten attack card index and card from the hand which beats it
+    pub fn legal_defenses(& self, hand: & [cards::Card]) -> Vec<(usize, cards::Card)>
+    {
+        (0 .. self.played_cards.len())
+            .flat_map(|attack_card_index| hand.iter()
+                .filter(move |card| self.check_defense_card(card, attack_card_index).is_ok())
+                .map(move |card| (attack_card_index, *card)))
+            .collect()
+    }
+
+    pub fn check_transfer(& self, transfer: & Transfer, next_defender_cards_count: usize) -> Result<(), Error>
+    {
+        let (card, attack_cards_count) = match transfer
+        {
+            Transfer::Card(card) => (card, self.played_cards.len() + 1),
+            Transfer::ShowTrump(card) =>
+            {
+                if card.suit() != self.trump() || card.is_joker()
+                {
+                    return Err(Error::InvalidTrumpShow);
+                }
+                (card, self.played_cards.len())
+            },
+        };
+
+        if self.played_cards.is_empty()
+        {
+            Err(Error::NoCardsToBeat)
+        }
+        else if !self.is_defense_not_started()
+        {
+            Err(Error::TransferAfterDefense)
+        }
+        else if self.played_cards.iter().any(|(attack_card, _)| attack_card.value() != card.value())
+        {
+            Err(Error::AbsentCardValue(card.value()))
+        }
+        else if attack_cards_count > self.max_attack_cards_count()
+        {
+            Err(Error::TooManyAttackCards(self.max_attack_cards_count()))
+        }
+        else if next_defender_cards_count < attack_cards_count
+        {
+            Err(Error::NotEnoughCardsToTransfer(next_defender_cards_count))
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+
     pub fn take_defense_card(&mut self, defense_card: cards::Card, attack_card_index: usize)
     {
-        self.defense_cards.insert(attack_card_index, defense_card);
+        debug_assert!(self.played_cards[attack_card_index].1.is_none());
+        self.played_cards[attack_card_index].1 = Some(defense_card);
+    }
+
+    /// No attack card is beaten yet
+    pub fn is_defense_not_started(& self) -> bool
+    {
+        self.played_cards.iter().all(|(_, defense_card)| defense_card.is_none())
+    }
+
+    /// Played cards which look illegal: throw-ins of values absent on the table and defenses that don't beat
+    pub fn suspicious_cards(& self) -> Vec<cards::Card>
+    {
+        let mut cards = vec![];
+        let played_cards: Vec<cards::Card> = self.played_cards.iter()
+            .flat_map(|(attack_card, defense_card)| std::iter::once(*attack_card).chain(*defense_card))
+            .collect();
+        for (attack_card, _) in self.played_cards.iter().skip(1)
+        {
+            let is_value_played = played_cards.iter()
+                .any(|played_card| played_card != attack_card && played_card.value() == attack_card.value());
+            if !is_value_played
+            {
+                cards.push(*attack_card);
+            }
+        }
+
+        for (attack_card_index, (_, defense_card)) in self.played_cards.iter().enumerate()
+        {
+            if let Some(defense_card) = defense_card
+            {
+                if !self.can_beat(defense_card, attack_card_index)
+                {
+                    cards.push(*defense_card);
+                }
+            }
+        }
+        cards
     }
 
     // --- transfer cards ---
 
     pub fn discard_cards(&mut self)
     {
-        self.discarded_cards.append(&mut self.attack_cards);
-        self.discarded_cards.append(&mut self.defense_cards);
+        self.is_first_bout = false;
+        let played_cards = self.played_cards.drain(..)
+            .flat_map(|(attack_card, defense_card)| std::iter::once(attack_card).chain(defense_card));
+        self.discarded_cards.extend(played_cards);
+    }
+
+    /// Discarded cards are drawn as penalty when the stock is empty
+    pub fn draw_discarded_cards(&mut self, count: usize) -> impl Iterator<Item = cards::Card> + '_
+    {
+        let range = positive_sub_or_zero(self.discarded_cards.len(), count)..;
+        self.discarded_cards.drain(range)
+    }
+
+    /// Only for solving positions: the stock is over, only the trump card is remembered and given cards are on the table
+    pub fn set_endgame(&mut self, trump_card: cards::Card, played_cards: Vec<(cards::Card, Option<cards::Card>)>)
+    {
+        self.card_stock.clear();
+        self.discarded_cards.clear();
+        self.trump_card = trump_card;
+        self.played_cards = played_cards;
+        self.is_first_bout = false;
+    }
+
+    /// Only for simulations: stock cards are unknown to players, so they are replaced with guessed ones,
+    /// the trump card stays at the bottom
+    pub fn replace_stock(&mut self, cards: Vec<cards::Card>)
+    {
+        debug_assert_eq!(cards.len() + 1, self.card_stock.len());
+        self.card_stock.truncate(1);
+        self.card_stock.extend(cards);
     }
 
     pub fn draw_stock_cards(&mut self, count: usize) -> Option<impl Iterator<Item = cards::Card> + '_>
@@ -176,15 +411,15 @@ impl Table
         Some(self.card_stock.drain(range))
     }
 
-    pub fn draw_played_cards(&mut self) -> impl Iterator<Item = cards::Card> + '_
+    pub fn draw_played_cards(&mut self) -> Result<impl Iterator<Item = cards::Card> + '_, Error>
     {
-        if self.attack_cards.len() == 0
+        if self.played_cards.is_empty()
         {
-            panic!("There isn't any attack card to draw");
+            return Err(Error::NoPlayedCards);
         }
-        let attack_cards = self.attack_cards.drain(..);
-        let defense_cards = self.defense_cards.drain(..);
-        attack_cards.chain(defense_cards).into_iter()
+        self.is_first_bout = false;
+        Ok(self.played_cards.drain(..)
+            .flat_map(|(attack_card, defense_card)| std::iter::once(attack_card).chain(defense_card)))
     }
 }
 
@@ -192,50 +427,46 @@ impl std::fmt::Display for Table
 {
     fn fmt(& self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error>
     {
-        writeln!(f, "Cards remain: {}, trump: {}", self.remain_cards_count(), self.trump())?;
-        for _ in 0 .. self.attack_cards.len()
+        writeln!(f, "Cards remain: {}, trump card: {}{}", self.remain_cards_count(), self.trump_card(),
+            if self.is_trump_card_drawn() {" (drawn)"} else {""})?;
+        for _ in self.played_cards.iter()
         {
             write!(f, " ┌────┐ ")?;
         }
         writeln!(f);
 
-        for card in self.attack_cards.iter()
+        for (attack_card, _) in self.played_cards.iter()
         {
-            write!(f, " │{card} │ ")?;
+            write!(f, " │{attack_card} │ ")?;
         }
         writeln!(f);
-              for _ in 0 .. self.defense_cards.len()
-        {
-            write!(f, " │┌───┴┐")?;
-        }
- 
-        for _ in self.defense_cards.len() .. self.attack_cards.len()
+
+        // defense card lies across the attack card it beats
+        for (_, defense_card) in self.played_cards.iter()
         {
-            write!(f, " │    │ ")?;
+            write!(f, "{}", if defense_card.is_some() {" │┌───┴┐"} else {" │    │ "})?;
         }
         writeln!(f);
-         
-        for card in self.defense_cards.iter()
-        {
-            write!(f, " └┤{card} │")?;
-        }
-        
-        for _ in self.defense_cards.len() .. self.attack_cards.len()
+
+        for (_, defense_card) in self.played_cards.iter()
         {
-            write!(f, " └────┘ ")?;
+            match defense_card
+            {
+                Some(defense_card) => write!(f, " └┤{defense_card} │")?,
+                None => write!(f, " └────┘ ")?,
+            }
         }
-        writeln!(f); 
+        writeln!(f);
 
- 
-        for _ in 0 .. self.defense_cards.len()
+        for (_, defense_card) in self.played_cards.iter()
         {
-            write!(f, "  │    │")?;
+            write!(f, "{}", if defense_card.is_some() {"  │    │"} else {"        "})?;
         }
         writeln!(f);
-        
-        for _ in 0 .. self.defense_cards.len()
+
+        for (_, defense_card) in self.played_cards.iter()
         {
-            write!(f, "  └────┘")?;
+            write!(f, "{}", if defense_card.is_some() {"  └────┘"} else {"        "})?;
         }
         writeln!(f);
 
@@ -4,12 +4,31 @@ use std::{io::Write, num::{ParseIntError, IntErrorKind}};
 pub enum Error
 {
     TooManyPlayers(usize),
-    TooManyAttackCards,
+    NotEnoughPlayers(usize),
+    UnevenTeams(usize),
+    TooManyAttackCards(usize),
+    NotEnoughDefenderCards(usize),
     AbsentCardValue(crate::cards::Value),
     NoCardsToBeat,
     InvalidAttackIndex(usize),
+    AttackCardBeaten(usize),
     InvalidDeckIndex(usize),
     IncorrectDefense,
+    UnbeatenAttackCards(usize),
+    TransferAfterDefense,
+    NotEnoughCardsToTransfer(usize),
+    InvalidTrumpShow,
+    InvalidCardValue(usize),
+    InvalidCardSuit(usize),
+    NoPlayedCards,
+    AttackNotStarted,
+    UnexpectedAction,
+    AbsentCard(crate::cards::Card),
+    InvalidArgument(String),
+    MissingArgumentValue(String),
+    InvalidCard(String),
+    NotEndgame,
+    SolverLimitExceeded(usize),
 }
 
 impl std::fmt::Display for Error
@@ -19,12 +38,31 @@ impl std::fmt::Display for Error
         write!(f, "{}", match self 
             {
                 Self::TooManyPlayers(max_count) => format!("Can't add more than {max_count} players to this game"),
-                Self::TooManyAttackCards        => "Maximum 6 attack cards".to_string(),
+                Self::NotEnoughPlayers(count)   => format!("There are not enough players in this game to start (need {count} more)"),
+                Self::UnevenTeams(count)        => format!("{count} players can't be split into two equal teams"),
+                Self::TooManyAttackCards(max_count) => format!("Maximum {max_count} attack cards"),
+                Self::NotEnoughDefenderCards(count) => format!("Defender has only {count} cards to beat attack"),
                 Self::AbsentCardValue(value)    => format!("There isn't such cards with value '{value}' on the table"),
                 Self::NoCardsToBeat             => "There isn't any card to beat".to_string(),
                 Self::InvalidAttackIndex(index) => format!("There isn't attack card at #{index}"),
+                Self::AttackCardBeaten(index)   => format!("Attack card at #{index} is already beaten"),
                 Self::InvalidDeckIndex(index)   => format!("You haven't card at #{index}"),
                 Self::IncorrectDefense           => "Given defense card can't beat given attack card".to_string(),
+                Self::UnbeatenAttackCards(count) => format!("All {count} unbeaten attack cards have to be beaten at once"),
+                Self::TransferAfterDefense      => "Attack can't be transfered after defense has started".to_string(),
+                Self::NotEnoughCardsToTransfer(count) => format!("Next defender has only {count} cards"),
+                Self::InvalidTrumpShow          => "Only a trump of the attack value can be shown once per round".to_string(),
+                Self::InvalidCardValue(number)  => format!("Can't create card value from number '{number}'"),
+                Self::InvalidCardSuit(number)   => format!("Can't create card suit from number '{number}'"),
+                Self::NoPlayedCards             => "There isn't any played card on the table".to_string(),
+                Self::AttackNotStarted          => "Attack has to be started with a card".to_string(),
+                Self::UnexpectedAction          => "This action can't be made now".to_string(),
+                Self::AbsentCard(card)          => format!("There isn't the {card} in the hand or on the table"),
+                Self::InvalidArgument(argument) => format!("Invalid argument '{argument}', see --help"),
+                Self::MissingArgumentValue(argument) => format!("Argument '{argument}' needs a value, see --help"),
+                Self::InvalidCard(text)         => format!("Can't read card from '{text}', cards look like '6S', '10H', 'AD' or 'RJ' for red joker"),
+                Self::NotEndgame                => "Only positions with empty stock and two players left can be solved".to_string(),
+                Self::SolverLimitExceeded(count) => format!("Position wasn't solved in {count} positions"),
             })
     }
 }
@@ -33,6 +71,9 @@ impl std::error::Error for Error
 {
 }
 
+/// Single random generator of the game, so seeded games can be reproduced
+pub type GameRng = rand::rngs::StdRng;
+
 pub const INDENT_SIZE: usize = 3;
 
 macro_rules! log